@@ -6,29 +6,39 @@ use lofty::config::WriteOptions;
 use base64::{Engine as _, engine::general_purpose};
 use std::fs;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Song {
-    path: String,
-    title: Option<String>,
-    artist: Option<String>,
-    album: Option<String>,
-    genre: Option<String>,
-    track_number: Option<u32>,
-    year: Option<u32>,
-    duration_seconds: u64,
-    bitrate: Option<u32>,        // kbps
-    sample_rate: Option<u32>,    // Hz
-    bits_per_sample: Option<u8>,
-    channels: Option<u8>,
-    file_size_bytes: u64,
-    has_album_art: bool,
-    cover_handle: Option<String>,
-    lyrics: Option<String>,
+    pub path: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub track_number: Option<u32>,
+    pub year: Option<u32>,
+    pub duration_seconds: u64,
+    pub bitrate: Option<u32>,        // kbps
+    pub sample_rate: Option<u32>,    // Hz
+    pub bits_per_sample: Option<u8>,
+    pub channels: Option<u8>,
+    pub file_size_bytes: u64,
+    #[serde(default)]
+    pub modified_secs: u64, // with the size, tells a rescan whether to read the file again
+    pub has_album_art: bool,
+    pub cover_handle: Option<String>,
+    pub lyrics: Option<String>,
+    pub replaygain_track_gain: Option<f32>, // dB
+    pub replaygain_track_peak: Option<f32>,
+    pub replaygain_album_gain: Option<f32>, // dB
+    pub replaygain_album_peak: Option<f32>,
+    #[serde(default)]
+    pub unplayable: bool, // failed to open or decode last time it was played
 }
 
 use rayon::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 use std::hash::{Hash, Hasher};
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::DefaultHasher;
 
 fn get_thumbnails_dir() -> PathBuf {
@@ -40,9 +50,9 @@ fn get_thumbnails_dir() -> PathBuf {
 }
 
 #[derive(Clone, Serialize)]
-struct ProgressPayload {
-    current: usize,
-    total: usize,
+pub(crate) struct ProgressPayload {
+    pub current: usize,
+    pub total: usize,
 }
 
 use tauri::Emitter;
@@ -54,153 +64,332 @@ use std::io::Cursor;
 use lofty::probe::Probe;
 use lofty::file::TaggedFileExt;
 use lofty::tag::Accessor;
+use crate::replaygain::ReplayGain;
+use crate::database;
+use crate::search;
+
+/// Counts of what a rescan found changed, sent along with its progress.
+#[derive(Clone, Serialize)]
+pub(crate) struct ScanProgressPayload {
+    pub current: usize,
+    pub total: usize, // files that need reading, unchanged ones are taken from the cache
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+#[derive(Serialize)]
+pub struct ScanResult {
+    pub songs: Vec<Song>,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+/// Reads the tags, properties and cover thumbnail of one file.
+fn read_song(path: &Path, file_size: u64, modified: u64, thumb_dir: &Path) -> Song {
+    // Files can vanish between listing and reading while the watcher is running
+    match Probe::open(path).and_then(|probe| probe.read()) {
+        Ok(tagged_file) => {
+            let tag = tagged_file.primary_tag();
+            let properties = tagged_file.properties();
+            
+            let title = tag.as_ref().and_then(|t| t.title().map(|s| s.to_string()));
+            let artist = tag.as_ref().and_then(|t| t.artist().map(|s| s.to_string()));
+            let album = tag.as_ref().and_then(|t| t.album().map(|s| s.to_string()));
+            let genre = tag.as_ref().and_then(|t| t.genre().map(|s| s.to_string()));
+            let track_number = tag.as_ref().and_then(|t| t.track());
+            let year = tag.as_ref().and_then(|t| t.year());
+            let lyrics = tag.as_ref().and_then(|t| t.get_string(&lofty::tag::ItemKey::Lyrics).map(|s| s.to_string()));
+            let replaygain = tag.map(ReplayGain::from_tag).unwrap_or_default();
+            
+            // Art Extraction & Resizing
+            let mut has_album_art = false;
+            let mut cover_handle = None;
+            
+            if let Some(t) = tag {
+                let pictures = t.pictures();
+                if let Some(pic) = pictures.first() {
+                     has_album_art = true;
+                     
+                     // Process image: Resize to thumbnail
+                     if let Ok(img) = image::load_from_memory(pic.data()) {
+                         let thumbnail = img.resize(250, 250, FilterType::Lanczos3);
+                         
+                         let mut thumb_bytes: Vec<u8> = Vec::new();
+                         if thumbnail.write_to(&mut Cursor::new(&mut thumb_bytes), ImageFormat::Jpeg).is_ok() {
+                             // Hash the RESIZED bytes for the handle
+                             let mut hasher = DefaultHasher::new();
+                             thumb_bytes.hash(&mut hasher);
+                             let hash = hasher.finish();
+                             let hash_str = format!("{:x}", hash);
+                             
+                             let mut current_thumb_path = thumb_dir.to_path_buf();
+                             current_thumb_path.push(format!("{}.bin", hash_str));
+                             
+                             // Save only if doesn't exist (deduplication)
+                             if !current_thumb_path.exists() {
+                                 let _ = fs::write(&current_thumb_path, &thumb_bytes);
+                             }
+                             cover_handle = Some(hash_str);
+                         }
+                     }
+                }
+            }
+
+            Song {
+                path: path.to_string_lossy().to_string(),
+                title: title.or_else(|| Some(path.file_name().unwrap_or_default().to_string_lossy().to_string())),
+                artist,
+                album,
+                genre,
+                track_number,
+                year,
+                duration_seconds: properties.duration().as_secs(),
+                bitrate: properties.audio_bitrate(),
+                sample_rate: properties.sample_rate(),
+                bits_per_sample: properties.bit_depth(),
+                channels: properties.channels(),
+                file_size_bytes: file_size,
+                modified_secs: modified,
+                has_album_art,
+                cover_handle,
+                lyrics,
+                replaygain_track_gain: replaygain.track_gain,
+                replaygain_track_peak: replaygain.track_peak,
+                replaygain_album_gain: replaygain.album_gain,
+                replaygain_album_peak: replaygain.album_peak,
+                unplayable: false,
+            }
+        },
+        Err(_) => {
+            // Fallback for untagged files
+            Song {
+                path: path.to_string_lossy().to_string(),
+                title: Some(path.file_name().unwrap_or_default().to_string_lossy().to_string()),
+                artist: None,
+                album: None,
+                genre: None,
+                track_number: None,
+                year: None,
+                duration_seconds: 0,
+                bitrate: None,
+                sample_rate: None,
+                bits_per_sample: None,
+                channels: None,
+                file_size_bytes: file_size,
+                modified_secs: modified,
+                has_album_art: false,
+                cover_handle: None,
+                lyrics: None,
+                replaygain_track_gain: None,
+                replaygain_track_peak: None,
+                replaygain_album_gain: None,
+                replaygain_album_peak: None,
+                unplayable: false,
+            }
+        }
+    }
+}
 
+fn modified_secs(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+pub(crate) fn is_audio_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        let ext = ext.to_string_lossy().to_lowercase();
+        matches!(ext.as_str(), "mp3" | "flac" | "wav" | "m4a" | "ogg")
+    })
+}
+
+/// Audio files under `root` (or `root` itself if it is one) with their size and mtime.
+fn audio_files(root: &Path) -> Vec<(PathBuf, u64, u64)> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_audio_file(e.path()))
+        .map(|e| {
+            let metadata = e.metadata().ok();
+            let size = metadata.as_ref().map_or(0, |m| m.len());
+            let modified = metadata.as_ref().map_or(0, modified_secs);
+            (e.into_path(), size, modified)
+        })
+        .collect()
+}
+
+fn is_unchanged(song: &Song, size: u64, modified: u64) -> bool {
+    // An unknown mtime can't tell us anything, so such files are always read
+    song.file_size_bytes == size && song.modified_secs == modified && modified != 0
+}
+
+/// Scans `directory` for audio files. Files whose size and modification time match the
+/// library cache keep their cached entry, only new and changed ones are read again.
 #[tauri::command]
-pub async fn scan_music_dir(app: tauri::AppHandle, directory: String) -> Result<Vec<Song>, String> {
+pub async fn scan_music_dir(app: tauri::AppHandle, directory: String) -> Result<ScanResult, String> {
     let root_path = PathBuf::from(directory);
     let thumb_dir = get_thumbnails_dir();
 
-    // Collect all valid audio file paths first
-    let entries: Vec<_> = WalkDir::new(&root_path)
+    // Collect all valid audio file paths first, with what to compare against the cache
+    let files = audio_files(&root_path);
+
+    let mut cached: HashMap<String, Song> = get_cached_library()
+        .unwrap_or_default()
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| {
-             let ext = ext.to_string_lossy().to_lowercase();
-             matches!(ext.as_str(), "mp3" | "flac" | "wav" | "m4a" | "ogg")
-        }))
+        .map(|s| (s.path.clone(), s))
         .collect();
-    
-    let total_songs = entries.len();
+
+    // Unchanged files keep their slot filled from the cache, the rest are read below
+    let mut slots: Vec<Option<Song>> = Vec::with_capacity(files.len());
+    let mut to_read = Vec::new();
+    let (mut added, mut updated) = (0, 0);
+    for (index, (path, size, modified)) in files.iter().enumerate() {
+        match cached.remove(path.to_string_lossy().as_ref()) {
+            Some(song) if is_unchanged(&song, *size, *modified) => {
+                slots.push(Some(song));
+                continue;
+            },
+            Some(_) => updated += 1,
+            None => added += 1,
+        }
+        slots.push(None);
+        to_read.push(index);
+    }
+    // Whatever is left in the cache is no longer on disk
+    let removed_paths: Vec<String> = cached.into_keys().collect();
+    let removed = removed_paths.len();
+
+    let total = to_read.len();
+    let progress = |current: usize| ScanProgressPayload { current, total, added, updated, removed };
     let processed = Arc::new(AtomicUsize::new(0));
     let app_clone = app.clone();
+    if total == 0 {
+        let _ = app.emit("sync-progress", progress(0));
+    }
 
     // Process files in parallel using rayon
-    let songs: Vec<Song> = entries.par_iter().map(|entry| {
-        let path = entry.path();
-        let file_size = entry.metadata().map(|m| m.len()).unwrap_or(0);
-        
-        let song = match Probe::open(path).expect("ERROR: Bad path provided!").read() {
-            Ok(tagged_file) => {
-                let tag = tagged_file.primary_tag();
-                let properties = tagged_file.properties();
-                
-                let title = tag.as_ref().and_then(|t| t.title().map(|s| s.to_string()));
-                let artist = tag.as_ref().and_then(|t| t.artist().map(|s| s.to_string()));
-                let album = tag.as_ref().and_then(|t| t.album().map(|s| s.to_string()));
-                let genre = tag.as_ref().and_then(|t| t.genre().map(|s| s.to_string()));
-                let track_number = tag.as_ref().and_then(|t| t.track());
-                let year = tag.as_ref().and_then(|t| t.year());
-                let lyrics = tag.as_ref().and_then(|t| t.get_string(&lofty::tag::ItemKey::Lyrics).map(|s| s.to_string()));
-                
-                // Art Extraction & Resizing
-                let mut has_album_art = false;
-                let mut cover_handle = None;
-                
-                if let Some(t) = tag {
-                    let pictures = t.pictures();
-                    if let Some(pic) = pictures.first() {
-                         has_album_art = true;
-                         
-                         // Process image: Resize to thumbnail
-                         if let Ok(img) = image::load_from_memory(pic.data()) {
-                             let thumbnail = img.resize(250, 250, FilterType::Lanczos3);
-                             
-                             let mut thumb_bytes: Vec<u8> = Vec::new();
-                             if thumbnail.write_to(&mut Cursor::new(&mut thumb_bytes), ImageFormat::Jpeg).is_ok() {
-                                 // Hash the RESIZED bytes for the handle
-                                 let mut hasher = DefaultHasher::new();
-                                 thumb_bytes.hash(&mut hasher);
-                                 let hash = hasher.finish();
-                                 let hash_str = format!("{:x}", hash);
-                                 
-                                 let mut current_thumb_path = thumb_dir.clone(); // Clone for each thread
-                                 current_thumb_path.push(format!("{}.bin", hash_str));
-                                 
-                                 // Save only if doesn't exist (deduplication)
-                                 if !current_thumb_path.exists() {
-                                     let _ = fs::write(&current_thumb_path, &thumb_bytes);
-                                 }
-                                 cover_handle = Some(hash_str);
-                             }
-                         }
-                    }
-                }
-
-                Song {
-                    path: path.to_string_lossy().to_string(),
-                    title: title.or_else(|| Some(path.file_name().unwrap_or_default().to_string_lossy().to_string())),
-                    artist,
-                    album,
-                    genre,
-                    track_number,
-                    year,
-                    duration_seconds: properties.duration().as_secs(),
-                    bitrate: properties.audio_bitrate(),
-                    sample_rate: properties.sample_rate(),
-                    bits_per_sample: properties.bit_depth(),
-                    channels: properties.channels(),
-                    file_size_bytes: file_size,
-                    has_album_art,
-                    cover_handle,
-                    lyrics,
-                }
-            },
-            Err(_) => {
-                // Fallback for untagged files
-                Song {
-                    path: path.to_string_lossy().to_string(),
-                    title: Some(path.file_name().unwrap_or_default().to_string_lossy().to_string()),
-                    artist: None,
-                    album: None,
-                    genre: None,
-                    track_number: None,
-                    year: None,
-                    duration_seconds: 0,
-                    bitrate: None,
-                    sample_rate: None,
-                    bits_per_sample: None,
-                    channels: None,
-                    file_size_bytes: file_size,
-                    has_album_art: false,
-                    cover_handle: None,
-                    lyrics: None,
-                }
-            }
-        };
+    let read: Vec<(usize, Song)> = to_read.par_iter().map(|&index| {
+        let (path, size, modified) = &files[index];
+        let song = read_song(path, *size, *modified, &thumb_dir);
 
         // Emit progress
         let count = processed.fetch_add(1, Ordering::SeqCst) + 1;
-        if count % 10 == 0 || count == total_songs {
-            let _ = app_clone.emit("sync-progress", ProgressPayload {
-                current: count,
-                total: total_songs,
-            });
+        if count % 10 == 0 || count == total {
+            let _ = app_clone.emit("sync-progress", progress(count));
         }
 
-        song
+        (index, song)
     }).collect();
 
+    let mut changed = Vec::with_capacity(read.len());
+    for (index, song) in read {
+        changed.push(song.clone());
+        slots[index] = Some(song);
+    }
+    let songs: Vec<Song> = slots.into_iter().flatten().collect();
+
     // 3. Save to cache
-    if let Err(e) = save_library_cache(&songs) {
-        eprintln!("Failed to save library cache: {}", e);
+    if added + updated + removed > 0 {
+        if let Err(e) = database::save_songs(&changed, &removed_paths) {
+            eprintln!("Failed to save library cache: {}", e);
+        }
+        let touched: Vec<String> = changed.iter().map(|s| s.path.clone()).chain(removed_paths).collect();
+        search::update(&touched);
+    }
+
+    Ok(ScanResult { songs, added, updated, removed })
+}
+
+/// What changed in the library cache after some of its files changed on disk.
+#[derive(Clone, Serialize, Default)]
+pub struct LibraryChanges {
+    pub added: Vec<Song>,
+    pub updated: Vec<Song>,
+    pub removed: Vec<String>,
+}
+
+impl LibraryChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
     }
-    
-    Ok(songs)
+}
+
+/// Brings the library cache up to date for just `paths`, which may be files or
+/// directories. Songs at or under a path that no longer exists are removed, audio files
+/// found there are read the same way a full scan reads them.
+pub(crate) fn refresh_library_paths(paths: &[PathBuf]) -> Result<LibraryChanges, String> {
+    let mut files: Vec<(PathBuf, u64, u64)> = Vec::new();
+    for path in paths {
+        if path.exists() {
+            files.extend(audio_files(path));
+        }
+    }
+    files.sort();
+    files.dedup_by(|a, b| a.0 == b.0);
+
+    let cached: HashMap<String, Song> = get_cached_library()?.into_iter().map(|s| (s.path.clone(), s)).collect();
+    let to_read: Vec<&(PathBuf, u64, u64)> = files
+        .iter()
+        .filter(|(path, size, modified)| {
+            cached.get(path.to_string_lossy().as_ref()).is_none_or(|song| !is_unchanged(song, *size, *modified))
+        })
+        .collect();
+
+    let thumb_dir = get_thumbnails_dir();
+    let read: Vec<Song> = to_read
+        .par_iter()
+        .map(|(path, size, modified)| read_song(path, *size, *modified, &thumb_dir))
+        .collect();
+
+    let mut changes = LibraryChanges::default();
+    let found: HashSet<&Path> = files.iter().map(|(path, _, _)| path.as_path()).collect();
+    changes.removed = cached
+        .keys()
+        .filter(|path| {
+            let path = Path::new(path);
+            paths.iter().any(|p| path.starts_with(p)) && !found.contains(path)
+        })
+        .cloned()
+        .collect();
+    for song in read {
+        if cached.contains_key(&song.path) {
+            changes.updated.push(song);
+        } else {
+            changes.added.push(song);
+        }
+    }
+
+    if !changes.is_empty() {
+        let saved: Vec<Song> = changes.added.iter().chain(&changes.updated).cloned().collect();
+        database::save_songs(&saved, &changes.removed)?;
+        let touched: Vec<String> = saved.into_iter().map(|s| s.path).chain(changes.removed.iter().cloned()).collect();
+        search::update(&touched);
+    }
+    Ok(changes)
 }
 
 #[tauri::command]
 pub fn clear_cache() -> Result<(), String> {
-    let lib_path = get_library_path();
     let thumb_dir = get_thumbnails_dir();
     
-    if lib_path.exists() {
-        let _ = fs::remove_file(lib_path);
-    }
+    database::clear_songs()?;
+    crate::loudness::clear()?;
+    search::invalidate();
     
     if thumb_dir.exists() {
         let _ = fs::remove_dir_all(&thumb_dir);
         let _ = fs::create_dir_all(&thumb_dir);
     }
+
+    let waveforms_dir = crate::waveform::get_waveforms_dir();
+    if waveforms_dir.exists() {
+        let _ = fs::remove_dir_all(&waveforms_dir);
+        let _ = fs::create_dir_all(&waveforms_dir);
+    }
     
     Ok(())
 }
@@ -209,17 +398,17 @@ pub fn clear_cache() -> Result<(), String> {
 pub fn get_cache_size() -> Result<u64, String> {
     let mut total_size = 0;
     
-    let lib_path = get_library_path();
-    if let Ok(metadata) = fs::metadata(lib_path) {
+    if let Ok(metadata) = fs::metadata(database::get_database_path()) {
         total_size += metadata.len();
     }
-    
-    let thumb_dir = get_thumbnails_dir();
-    if let Ok(entries) = fs::read_dir(thumb_dir) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    total_size += metadata.len();
+
+    for dir in [get_thumbnails_dir(), crate::waveform::get_waveforms_dir()] {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if let Ok(metadata) = entry.metadata() {
+                    if metadata.is_file() {
+                        total_size += metadata.len();
+                    }
                 }
             }
         }
@@ -271,38 +460,24 @@ pub fn get_song_art(path: String) -> Result<Option<String>, String> {
     }
 }
 
-fn get_library_path() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap_or(PathBuf::from("."));
-    path.push("luma");
-    let _ = fs::create_dir_all(&path);
-    path.push("library.json");
-    path
-}
-
-fn save_library_cache(songs: &Vec<Song>) -> Result<(), String> {
-    let path = get_library_path();
-    let json = serde_json::to_string(songs).map_err(|e| e.to_string())?;
-    
-    use std::io::Write;
-    let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-    file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
-    Ok(())
+/// Flags or clears `path` as unplayable in the library cache, returning whether it changed.
+pub(crate) fn mark_unplayable(path: &str, unplayable: bool) -> Result<bool, String> {
+    database::set_unplayable(path, unplayable)
 }
 
 #[tauri::command]
 pub fn get_cached_library() -> Result<Vec<Song>, String> {
-    let path = get_library_path();
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
-
-    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let songs: Vec<Song> = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-    Ok(songs)
+    database::load_songs()
 }
 
-use crate::player::AudioPlayer;
+use crate::player::{self, AudioPlayer, OutputDevice, PlayerState, SeekTo, SleepAction, SleepTimerStatus};
+use crate::queue::{QueueState, RepeatMode};
+use crate::settings::{load_settings, write_settings};
+use crate::speed::{MAX_SPEED, MIN_SPEED};
+use crate::stereo::StereoSettings;
+use crate::visualizer::VisualizerSettings;
 use tauri::State;
+use std::time::Duration;
 
 #[tauri::command]
 pub fn play_track(state: State<'_, AudioPlayer>, path: String) -> Result<(), String> {
@@ -319,9 +494,51 @@ pub fn stop_playback(state: State<'_, AudioPlayer>) -> Result<(), String> {
     state.stop()
 }
 
+// Seeks wait for the audio thread and answer with the position in ms they landed on,
+// so they are async to keep that wait off the main thread and the async runtime
+
+#[tauri::command]
+pub async fn seek_track(state: State<'_, AudioPlayer>, seconds: u64) -> Result<u64, String> {
+    let landed = state.seek(SeekTo::Absolute(Duration::from_secs(seconds))).await?;
+    Ok(landed.as_millis() as u64)
+}
+
+#[tauri::command]
+pub async fn seek_track_ms(state: State<'_, AudioPlayer>, position_ms: u64) -> Result<u64, String> {
+    let landed = state.seek(SeekTo::Absolute(Duration::from_millis(position_ms))).await?;
+    Ok(landed.as_millis() as u64)
+}
+
+#[tauri::command]
+pub async fn seek_relative(state: State<'_, AudioPlayer>, delta_ms: i64) -> Result<u64, String> {
+    let landed = state.seek(SeekTo::Relative { millis: delta_ms }).await?;
+    Ok(landed.as_millis() as u64)
+}
+
+/// Seeks forward by the seek interval from the settings.
+#[tauri::command]
+pub async fn seek_forward(state: State<'_, AudioPlayer>) -> Result<u64, String> {
+    let interval = load_settings()?.seek_interval.saturating_mul(1000) as i64;
+    let landed = state.seek(SeekTo::Relative { millis: interval }).await?;
+    Ok(landed.as_millis() as u64)
+}
+
+/// Seeks back by the seek interval from the settings.
+#[tauri::command]
+pub async fn seek_backward(state: State<'_, AudioPlayer>) -> Result<u64, String> {
+    let interval = load_settings()?.seek_interval.saturating_mul(1000) as i64;
+    let landed = state.seek(SeekTo::Relative { millis: -interval }).await?;
+    Ok(landed.as_millis() as u64)
+}
+
+#[tauri::command]
+pub fn set_ab_loop(state: State<'_, AudioPlayer>, start_ms: u64, end_ms: u64) -> Result<(), String> {
+    state.set_loop(Some((Duration::from_millis(start_ms), Duration::from_millis(end_ms))))
+}
+
 #[tauri::command]
-pub fn seek_track(state: State<'_, AudioPlayer>, seconds: u64) -> Result<(), String> {
-    state.seek(seconds)
+pub fn clear_ab_loop(state: State<'_, AudioPlayer>) -> Result<(), String> {
+    state.set_loop(None)
 }
 
 #[tauri::command]
@@ -329,6 +546,151 @@ pub fn set_player_volume(state: State<'_, AudioPlayer>, volume: f32) -> Result<(
     state.set_volume(volume)
 }
 
+#[tauri::command]
+pub fn set_queue(state: State<'_, AudioPlayer>, paths: Vec<String>, start_index: Option<usize>) -> Result<(), String> {
+    state.set_queue(paths, start_index)
+}
+
+#[tauri::command]
+pub fn next_track(state: State<'_, AudioPlayer>) -> Result<(), String> {
+    state.next()
+}
+
+#[tauri::command]
+pub fn previous_track(state: State<'_, AudioPlayer>) -> Result<(), String> {
+    state.previous()
+}
+
+#[tauri::command]
+pub fn jump_to_queue_index(state: State<'_, AudioPlayer>, index: usize) -> Result<(), String> {
+    state.jump(index)
+}
+
+#[tauri::command]
+pub fn insert_next_tracks(state: State<'_, AudioPlayer>, paths: Vec<String>) -> Result<(), String> {
+    state.insert_next(paths)
+}
+
+#[tauri::command]
+pub fn append_tracks(state: State<'_, AudioPlayer>, paths: Vec<String>) -> Result<(), String> {
+    state.append(paths)
+}
+
+#[tauri::command]
+pub fn move_queue_track(state: State<'_, AudioPlayer>, from: usize, to: usize) -> Result<(), String> {
+    state.move_track(from, to)
+}
+
+#[tauri::command]
+pub fn remove_queue_track(state: State<'_, AudioPlayer>, index: usize) -> Result<(), String> {
+    state.remove_track(index)
+}
+
+#[tauri::command]
+pub fn clear_play_queue(state: State<'_, AudioPlayer>) -> Result<(), String> {
+    state.clear_queue()
+}
+
+#[tauri::command]
+pub fn set_shuffle(state: State<'_, AudioPlayer>, enabled: bool) -> Result<(), String> {
+    state.set_shuffle(enabled)
+}
+
+#[tauri::command]
+pub fn set_repeat_mode(state: State<'_, AudioPlayer>, mode: RepeatMode) -> Result<(), String> {
+    state.set_repeat(mode)
+}
+
+#[tauri::command]
+pub fn get_queue(state: State<'_, AudioPlayer>) -> Result<QueueState, String> {
+    state.queue()
+}
+
+#[tauri::command]
+pub fn get_player_state(state: State<'_, AudioPlayer>) -> Result<PlayerState, String> {
+    state.state()
+}
+
+#[tauri::command]
+pub fn set_playback_speed(state: State<'_, AudioPlayer>, speed: f32, preserve_pitch: bool) -> Result<(), String> {
+    let mut settings = load_settings()?;
+    settings.playback_speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    settings.preserve_pitch = preserve_pitch;
+    write_settings(&settings)?;
+    state.set_speed(settings.playback_speed, preserve_pitch)
+}
+
+#[tauri::command]
+pub fn set_stereo(state: State<'_, AudioPlayer>, stereo: StereoSettings) -> Result<(), String> {
+    let mut settings = load_settings()?;
+    settings.stereo = stereo.clone();
+    write_settings(&settings)?;
+    state.set_stereo(stereo)
+}
+
+#[tauri::command]
+pub fn set_visualizer(state: State<'_, AudioPlayer>, visualizer: VisualizerSettings) -> Result<(), String> {
+    let mut settings = load_settings()?;
+    settings.visualizer = visualizer.clone();
+    write_settings(&settings)?;
+    state.set_visualizer(visualizer);
+    Ok(())
+}
+
+// Default length of the sleep timer's volume fade
+const SLEEP_FADE_SECONDS: f32 = 30.0;
+
+fn sleep_fade(fade_seconds: Option<f32>) -> Duration {
+    Duration::from_secs_f32(fade_seconds.unwrap_or(SLEEP_FADE_SECONDS).clamp(0.0, 600.0))
+}
+
+#[tauri::command]
+pub fn set_sleep_timer(
+    state: State<'_, AudioPlayer>,
+    minutes: f32,
+    action: SleepAction,
+    fade_seconds: Option<f32>,
+) -> Result<(), String> {
+    if !minutes.is_finite() || minutes <= 0.0 {
+        return Err("Sleep timer needs a positive number of minutes".to_string());
+    }
+    state.sleep_after(Duration::from_secs_f32(minutes * 60.0), action, sleep_fade(fade_seconds))
+}
+
+/// `tracks` counts the current track, so 1 stops at the end of it.
+#[tauri::command]
+pub fn set_sleep_timer_tracks(
+    state: State<'_, AudioPlayer>,
+    tracks: u32,
+    action: SleepAction,
+    fade_seconds: Option<f32>,
+) -> Result<(), String> {
+    state.sleep_after_tracks(tracks, action, sleep_fade(fade_seconds))
+}
+
+#[tauri::command]
+pub fn cancel_sleep_timer(state: State<'_, AudioPlayer>) -> Result<(), String> {
+    state.cancel_sleep_timer()
+}
+
+#[tauri::command]
+pub fn get_sleep_timer(state: State<'_, AudioPlayer>) -> Result<Option<SleepTimerStatus>, String> {
+    Ok(state.state()?.sleep_timer)
+}
+
+#[tauri::command]
+pub fn get_output_devices() -> Result<Vec<OutputDevice>, String> {
+    player::list_output_devices()
+}
+
+#[tauri::command]
+pub fn set_output_device(state: State<'_, AudioPlayer>, name: Option<String>) -> Result<(), String> {
+    let mut settings = load_settings()?;
+    settings.output_device = name.clone();
+    write_settings(&settings)?;
+    state.set_output_device(name)
+}
+
 #[tauri::command]
 pub fn update_song_metadata(
     path: String,
@@ -380,5 +742,11 @@ pub fn update_song_metadata(
          return Err(format!("Failed to save metadata: {}", e));
     }
 
+    // Read the file back so the library and search see the new tags right away
+    let metadata = fs::metadata(&path_buf).map_err(|e| e.to_string())?;
+    let song = read_song(&path_buf, metadata.len(), modified_secs(&metadata), &get_thumbnails_dir());
+    database::save_songs(std::slice::from_ref(&song), &[])?;
+    search::update(&[song.path]);
+
     Ok(())
 }
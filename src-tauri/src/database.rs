@@ -0,0 +1,547 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use crate::commands::Song;
+use crate::loudness::{self, LoudnessInfo};
+use crate::playlist::Playlist;
+
+// Each entry upgrades the schema by one version, tracked in `PRAGMA user_version`.
+// Never edit a released migration, add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE artists (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+
+    CREATE TABLE albums (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        artist_id INTEGER REFERENCES artists(id),
+        UNIQUE (title, artist_id)
+    );
+    CREATE INDEX albums_artist ON albums(artist_id);
+
+    CREATE TABLE songs (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE,
+        title TEXT,
+        artist_id INTEGER REFERENCES artists(id),
+        album_id INTEGER REFERENCES albums(id),
+        genre TEXT,
+        track_number INTEGER,
+        year INTEGER,
+        duration_seconds INTEGER NOT NULL,
+        bitrate INTEGER,
+        sample_rate INTEGER,
+        bits_per_sample INTEGER,
+        channels INTEGER,
+        file_size_bytes INTEGER NOT NULL,
+        modified_secs INTEGER NOT NULL DEFAULT 0,
+        has_album_art INTEGER NOT NULL DEFAULT 0,
+        cover_handle TEXT,
+        lyrics TEXT,
+        replaygain_track_gain REAL,
+        replaygain_track_peak REAL,
+        replaygain_album_gain REAL,
+        replaygain_album_peak REAL,
+        unplayable INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX songs_artist ON songs(artist_id);
+    CREATE INDEX songs_album ON songs(album_id, track_number);
+    CREATE INDEX songs_genre ON songs(genre);
+    CREATE INDEX songs_year ON songs(year);
+    CREATE INDEX songs_title ON songs(title);
+
+    CREATE TABLE playlists (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        created_at INTEGER NOT NULL
+    );
+
+    -- Tracks are kept by path so playlists survive their files leaving the library
+    CREATE TABLE playlist_tracks (
+        playlist_id INTEGER NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        path TEXT NOT NULL,
+        PRIMARY KEY (playlist_id, position)
+    );
+
+    CREATE TABLE play_history (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL,
+        played_at INTEGER NOT NULL -- Unix timestamp, 0 for plays imported without one
+    );
+    CREATE INDEX play_history_path ON play_history(path);
+
+    -- Lyrics saved or fetched by the user, embedded ones stay on the song
+    CREATE TABLE lyrics (
+        path TEXT PRIMARY KEY,
+        lyrics TEXT NOT NULL
+    );
+    "#,
+    // Library pages sort text case-insensitively, which the plain title index can't serve
+    r#"
+    DROP INDEX songs_title;
+    CREATE INDEX songs_title ON songs(title COLLATE NOCASE);
+    CREATE INDEX songs_genre_nocase ON songs(genre COLLATE NOCASE);
+    CREATE INDEX songs_bitrate ON songs(bitrate);
+    CREATE INDEX artists_name_nocase ON artists(name COLLATE NOCASE);
+    CREATE INDEX albums_title_nocase ON albums(title COLLATE NOCASE);
+    "#,
+    r#"
+    -- Measured for files without ReplayGain tags, stale once `modified` no longer matches
+    CREATE TABLE loudness (
+        path TEXT PRIMARY KEY,
+        integrated_lufs REAL NOT NULL,
+        true_peak REAL NOT NULL,
+        album_lufs REAL,
+        album_peak REAL,
+        modified INTEGER NOT NULL
+    );
+    "#,
+];
+
+// Columns read by `song_from_row`, in its order
+pub(crate) const SONG_SELECT: &str = "
+    SELECT s.path, s.title, ar.name, al.title, s.genre, s.track_number, s.year,
+           s.duration_seconds, s.bitrate, s.sample_rate, s.bits_per_sample, s.channels,
+           s.file_size_bytes, s.modified_secs, s.has_album_art, s.cover_handle, s.lyrics,
+           s.replaygain_track_gain, s.replaygain_track_peak, s.replaygain_album_gain,
+           s.replaygain_album_peak, s.unplayable
+    FROM songs s
+    LEFT JOIN artists ar ON ar.id = s.artist_id
+    LEFT JOIN albums al ON al.id = s.album_id";
+
+// Opened on first use and shared by every thread
+static DATABASE: Mutex<Option<Connection>> = Mutex::new(None);
+
+fn get_luma_dir() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or(PathBuf::from("."));
+    path.push("luma");
+    let _ = fs::create_dir_all(&path);
+    path
+}
+
+pub(crate) fn get_database_path() -> PathBuf {
+    get_luma_dir().join("library.db")
+}
+
+/// Brings the schema up to date, importing the JSON files in `dir` along the way.
+/// Returns the files that were imported.
+fn migrate(conn: &mut Connection, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let version: usize = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if version > MIGRATIONS.len() {
+        return Err(format!("Library database version {} is newer than this app supports", version));
+    }
+    if version == MIGRATIONS.len() {
+        return Ok(Vec::new());
+    }
+
+    let mut tx = conn.transaction().map_err(|e| e.to_string())?;
+    for migration in &MIGRATIONS[version..] {
+        tx.execute_batch(migration).map_err(|e| e.to_string())?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len()).map_err(|e| e.to_string())?;
+    let imported = import_json(&mut tx, dir, version)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
+fn open() -> Result<Connection, String> {
+    let mut conn = Connection::open(get_database_path()).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "WAL").map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "foreign_keys", true).map_err(|e| e.to_string())?;
+    let imported = migrate(&mut conn, &get_luma_dir())?;
+
+    // Moved aside rather than deleted, and only once they are safely in the database
+    for path in imported {
+        let mut backup = path.clone().into_os_string();
+        backup.push(".bak");
+        if let Err(e) = fs::rename(&path, backup) {
+            eprintln!("Failed to move aside {}: {}", path.display(), e);
+        }
+    }
+    Ok(conn)
+}
+
+/// Runs `f` on the shared connection, opening and migrating the database first if needed.
+pub(crate) fn with_db<T>(f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+    let mut db = DATABASE.lock().map_err(|_| "Failed to lock database".to_string())?;
+    if db.is_none() {
+        *db = Some(open()?);
+    }
+    let conn = db.as_mut().ok_or("Database is not open")?;
+    f(conn).map_err(|e| e.to_string())
+}
+
+#[derive(Deserialize, Default)]
+struct LegacyPlayStats {
+    counts: HashMap<String, u64>,
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &PathBuf) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Skipping unreadable {}: {}", path.display(), e);
+            None
+        },
+    }
+}
+
+/// Brings the JSON files earlier versions kept in `dir` into a database being migrated
+/// from `version`, returning the files and directories that were imported.
+fn import_json(tx: &mut Transaction, dir: &Path, version: usize) -> Result<Vec<PathBuf>, String> {
+    let mut imported = Vec::new();
+
+    // Moved into the database by migration 3
+    let loudness = dir.join("loudness.json");
+    if version < 3 {
+        if let Some(store) = read_json::<HashMap<String, LoudnessInfo>>(&loudness) {
+            for (path, info) in &store {
+                loudness::insert(tx, path, info).map_err(|e| e.to_string())?;
+            }
+            imported.push(loudness);
+        }
+    }
+    if version > 0 {
+        return Ok(imported);
+    }
+
+    let library = dir.join("library.json");
+    if let Some(songs) = read_json::<Vec<Song>>(&library) {
+        for song in &songs {
+            upsert_song(tx, song).map_err(|e| e.to_string())?;
+        }
+        imported.push(library);
+    }
+
+    let playlists = dir.join("playlists");
+    if let Ok(entries) = fs::read_dir(&playlists) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(playlist) = read_json::<Playlist>(&path) {
+                // One bad playlist shouldn't keep the rest of the library out
+                if let Err(e) = import_playlist(tx, playlist) {
+                    eprintln!("Skipping playlist {}: {}", path.display(), e);
+                }
+            }
+        }
+        imported.push(playlists);
+    }
+
+    let lyrics = dir.join("lyrics.json");
+    if let Some(store) = read_json::<HashMap<String, String>>(&lyrics) {
+        for (path, text) in store {
+            tx.execute("INSERT OR REPLACE INTO lyrics (path, lyrics) VALUES (?1, ?2)", params![path, text])
+                .map_err(|e| e.to_string())?;
+        }
+        imported.push(lyrics);
+    }
+
+    let analytics = dir.join("analytics.json");
+    if let Some(stats) = read_json::<LegacyPlayStats>(&analytics) {
+        let mut insert = tx
+            .prepare("INSERT INTO play_history (path, played_at) VALUES (?1, 0)")
+            .map_err(|e| e.to_string())?;
+        for (path, count) in stats.counts {
+            for _ in 0..count {
+                insert.execute([&path]).map_err(|e| e.to_string())?;
+            }
+        }
+        imported.push(analytics);
+    }
+
+    Ok(imported)
+}
+
+/// Adds a playlist from an earlier version, renamed when another one already has its
+/// name. Nothing of it is left behind when it fails.
+fn import_playlist(tx: &mut Transaction, mut playlist: Playlist) -> rusqlite::Result<()> {
+    let name = playlist.name.clone();
+    let mut copy = 1;
+    while tx.query_row("SELECT EXISTS (SELECT 1 FROM playlists WHERE name = ?1)", [&playlist.name], |row| row.get(0))? {
+        copy += 1;
+        playlist.name = format!("{} ({})", name, copy);
+    }
+    if playlist.name != name {
+        eprintln!("Playlist \"{}\" already exists, importing the other one as \"{}\"", name, playlist.name);
+    }
+
+    let savepoint = tx.savepoint()?;
+    insert_playlist(&savepoint, &playlist)?;
+    savepoint.commit()
+}
+
+pub(crate) fn insert_playlist(conn: &Connection, playlist: &Playlist) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO playlists (name, created_at) VALUES (?1, ?2)",
+        params![playlist.name, playlist.created_at],
+    )?;
+    let id = conn.last_insert_rowid();
+    for (position, path) in playlist.tracks.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO playlist_tracks (playlist_id, position, path) VALUES (?1, ?2, ?3)",
+            params![id, position, path],
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn song_from_row(row: &Row) -> rusqlite::Result<Song> {
+    Ok(Song {
+        path: row.get(0)?,
+        title: row.get(1)?,
+        artist: row.get(2)?,
+        album: row.get(3)?,
+        genre: row.get(4)?,
+        track_number: row.get(5)?,
+        year: row.get(6)?,
+        duration_seconds: row.get(7)?,
+        bitrate: row.get(8)?,
+        sample_rate: row.get(9)?,
+        bits_per_sample: row.get(10)?,
+        channels: row.get(11)?,
+        file_size_bytes: row.get(12)?,
+        modified_secs: row.get(13)?,
+        has_album_art: row.get(14)?,
+        cover_handle: row.get(15)?,
+        lyrics: row.get(16)?,
+        replaygain_track_gain: row.get(17)?,
+        replaygain_track_peak: row.get(18)?,
+        replaygain_album_gain: row.get(19)?,
+        replaygain_album_peak: row.get(20)?,
+        unplayable: row.get(21)?,
+    })
+}
+
+fn artist_id(tx: &Transaction, name: Option<&str>) -> rusqlite::Result<Option<i64>> {
+    let Some(name) = name else { return Ok(None) };
+    tx.execute("INSERT OR IGNORE INTO artists (name) VALUES (?1)", [name])?;
+    tx.query_row("SELECT id FROM artists WHERE name = ?1", [name], |row| row.get(0)).map(Some)
+}
+
+fn album_id(tx: &Transaction, title: Option<&str>, artist_id: Option<i64>) -> rusqlite::Result<Option<i64>> {
+    let Some(title) = title else { return Ok(None) };
+    // `IS` so albums without an artist match too, the UNIQUE constraint can't catch those
+    let existing = tx
+        .query_row(
+            "SELECT id FROM albums WHERE title = ?1 AND artist_id IS ?2",
+            params![title, artist_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if existing.is_some() {
+        return Ok(existing);
+    }
+    tx.execute("INSERT INTO albums (title, artist_id) VALUES (?1, ?2)", params![title, artist_id])?;
+    Ok(Some(tx.last_insert_rowid()))
+}
+
+fn upsert_song(tx: &Transaction, song: &Song) -> rusqlite::Result<()> {
+    let artist = artist_id(tx, song.artist.as_deref())?;
+    let album = album_id(tx, song.album.as_deref(), artist)?;
+    tx.execute(
+        "INSERT INTO songs (
+            path, title, artist_id, album_id, genre, track_number, year, duration_seconds,
+            bitrate, sample_rate, bits_per_sample, channels, file_size_bytes, modified_secs,
+            has_album_art, cover_handle, lyrics, replaygain_track_gain, replaygain_track_peak,
+            replaygain_album_gain, replaygain_album_peak, unplayable
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)
+        ON CONFLICT (path) DO UPDATE SET
+            title = excluded.title, artist_id = excluded.artist_id, album_id = excluded.album_id,
+            genre = excluded.genre, track_number = excluded.track_number, year = excluded.year,
+            duration_seconds = excluded.duration_seconds, bitrate = excluded.bitrate,
+            sample_rate = excluded.sample_rate, bits_per_sample = excluded.bits_per_sample,
+            channels = excluded.channels, file_size_bytes = excluded.file_size_bytes,
+            modified_secs = excluded.modified_secs, has_album_art = excluded.has_album_art,
+            cover_handle = excluded.cover_handle, lyrics = excluded.lyrics,
+            replaygain_track_gain = excluded.replaygain_track_gain,
+            replaygain_track_peak = excluded.replaygain_track_peak,
+            replaygain_album_gain = excluded.replaygain_album_gain,
+            replaygain_album_peak = excluded.replaygain_album_peak,
+            unplayable = excluded.unplayable",
+        params![
+            song.path,
+            song.title,
+            artist,
+            album,
+            song.genre,
+            song.track_number,
+            song.year,
+            song.duration_seconds,
+            song.bitrate,
+            song.sample_rate,
+            song.bits_per_sample,
+            song.channels,
+            song.file_size_bytes,
+            song.modified_secs,
+            song.has_album_art,
+            song.cover_handle,
+            song.lyrics,
+            song.replaygain_track_gain,
+            song.replaygain_track_peak,
+            song.replaygain_album_gain,
+            song.replaygain_album_peak,
+            song.unplayable,
+        ],
+    )?;
+    Ok(())
+}
+
+// Albums and artists exist only through their songs
+fn prune_orphans(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "DELETE FROM albums WHERE id NOT IN (SELECT album_id FROM songs WHERE album_id IS NOT NULL);
+         DELETE FROM artists
+         WHERE id NOT IN (SELECT artist_id FROM songs WHERE artist_id IS NOT NULL)
+           AND id NOT IN (SELECT artist_id FROM albums WHERE artist_id IS NOT NULL);",
+    )
+}
+
+/// Every song in the library, in the order they were first added.
+pub(crate) fn load_songs() -> Result<Vec<Song>, String> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!("{} ORDER BY s.id", SONG_SELECT))?;
+        let songs = stmt.query_map([], song_from_row)?.collect();
+        songs
+    })
+}
+
+/// The songs at `paths` that are in the library, in the order they were first added.
+pub(crate) fn load_songs_by_path(paths: &[String]) -> Result<Vec<Song>, String> {
+    with_db(|conn| {
+        let mut songs = Vec::with_capacity(paths.len());
+        // Stays well below SQLite's limit on parameters per statement
+        for chunk in paths.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = conn.prepare(&format!("{} WHERE s.path IN ({}) ORDER BY s.id", SONG_SELECT, placeholders))?;
+            for song in stmt.query_map(rusqlite::params_from_iter(chunk), song_from_row)? {
+                songs.push(song?);
+            }
+        }
+        Ok(songs)
+    })
+}
+
+/// Inserts or replaces `songs` and drops the `removed` paths in a single transaction.
+pub(crate) fn save_songs(songs: &[Song], removed: &[String]) -> Result<(), String> {
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        for song in songs {
+            upsert_song(&tx, song)?;
+        }
+        for path in removed {
+            tx.execute("DELETE FROM songs WHERE path = ?1", [path])?;
+        }
+        prune_orphans(&tx)?;
+        tx.commit()
+    })
+}
+
+/// Whether the library holds any songs inside `directory`.
+pub(crate) fn has_songs_in(directory: &Path) -> Result<bool, String> {
+    let mut prefix = directory.to_string_lossy().into_owned();
+    if !prefix.ends_with(std::path::MAIN_SEPARATOR) {
+        prefix.push(std::path::MAIN_SEPARATOR);
+    }
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM songs WHERE substr(path, 1, length(?1)) = ?1)",
+            [prefix],
+            |row| row.get(0),
+        )
+    })
+}
+
+/// Sets the unplayable flag on `path`, returning whether it changed.
+pub(crate) fn set_unplayable(path: &str, unplayable: bool) -> Result<bool, String> {
+    with_db(|conn| {
+        let changed = conn.execute(
+            "UPDATE songs SET unplayable = ?2 WHERE path = ?1 AND unplayable != ?2",
+            params![path, unplayable],
+        )?;
+        Ok(changed > 0)
+    })
+}
+
+/// Forgets every song, album and artist. Playlists, lyrics and play history are kept.
+pub(crate) fn clear_songs() -> Result<(), String> {
+    with_db(|conn| conn.execute_batch("DELETE FROM songs; DELETE FROM albums; DELETE FROM artists;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory standing in for the config directory of an earlier version.
+    fn legacy_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("luma-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("playlists")).unwrap();
+        dir
+    }
+
+    fn playlist_names(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT name FROM playlists ORDER BY name").unwrap();
+        stmt.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn import_renames_duplicate_playlist() {
+        let dir = legacy_dir("duplicate-playlist");
+        fs::write(
+            dir.join("playlists/road-trip.json"),
+            r#"{"name": "Road Trip", "created_at": 1, "tracks": ["/music/a.flac"]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("playlists/road-trip-2.json"),
+            r#"{"name": "Road Trip", "created_at": 2, "tracks": ["/music/b.flac", "/music/gone.flac"]}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("lyrics.json"), r#"{"/music/a.flac": "la la la"}"#).unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        let imported = migrate(&mut conn, &dir).unwrap();
+
+        assert_eq!(playlist_names(&conn), ["Road Trip", "Road Trip (2)"]);
+        let tracks: u32 = conn.query_row("SELECT COUNT(*) FROM playlist_tracks", [], |row| row.get(0)).unwrap();
+        assert_eq!(tracks, 3);
+        // Everything after the conflict still made it in
+        let lyrics: u32 = conn.query_row("SELECT COUNT(*) FROM lyrics", [], |row| row.get(0)).unwrap();
+        assert_eq!(lyrics, 1);
+        assert!(imported.contains(&dir.join("playlists")));
+        assert!(imported.contains(&dir.join("lyrics.json")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn import_skips_unreadable_playlist() {
+        let dir = legacy_dir("unreadable-playlist");
+        fs::write(dir.join("playlists/broken.json"), "{ not json").unwrap();
+        fs::write(
+            dir.join("playlists/mix.json"),
+            r#"{"name": "Mix", "created_at": 1, "tracks": []}"#,
+        )
+        .unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, &dir).unwrap();
+
+        assert_eq!(playlist_names(&conn), ["Mix"]);
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
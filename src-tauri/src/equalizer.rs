@@ -0,0 +1,477 @@
+use serde::{Serialize, Deserialize};
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+use tauri::State;
+use crate::player::AudioPlayer;
+use crate::settings::{load_settings, write_settings};
+
+// Number of samples between checks for new EQ settings
+const CHECK_INTERVAL: usize = 512;
+// One octave wide bands for the graphic EQ
+const GRAPHIC_Q: f64 = 1.41;
+pub const GRAPHIC_FREQUENCIES: [f32; 10] = [31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0];
+pub const MAX_GAIN_DB: f32 = 12.0;
+
+const PRESETS: [(&str, [f32; 10]); 10] = [
+    ("Flat", [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("Rock", [5.0, 4.0, 3.0, 1.0, -1.0, -1.0, 1.0, 3.0, 4.0, 5.0]),
+    ("Pop", [-1.0, 1.0, 3.0, 4.0, 3.0, 1.0, 0.0, -1.0, -1.0, -1.0]),
+    ("Jazz", [3.0, 2.0, 1.0, 2.0, -1.0, -1.0, 0.0, 1.0, 2.0, 3.0]),
+    ("Classical", [4.0, 3.0, 2.0, 1.0, -1.0, -1.0, 0.0, 2.0, 3.0, 4.0]),
+    ("Electronic", [5.0, 4.0, 1.0, 0.0, -2.0, 1.0, 0.0, 1.0, 4.0, 5.0]),
+    ("Bass Boost", [6.0, 5.0, 4.0, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("Treble Boost", [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0]),
+    ("Vocal", [-2.0, -2.0, -1.0, 1.0, 3.0, 4.0, 3.0, 1.0, 0.0, -1.0]),
+    ("Loudness", [5.0, 3.0, 0.0, 0.0, -1.0, 0.0, -1.0, 0.0, 3.0, 4.0]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+/// A user-defined band on top of the graphic EQ.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParametricBand {
+    pub kind: FilterKind,
+    pub frequency: f32, // Hz
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EqualizerSettings {
+    pub enabled: bool,
+    pub preset: Option<String>, // None once bands were edited by hand
+    pub preamp_db: f32,
+    pub bands: [f32; 10], // gains for GRAPHIC_FREQUENCIES in dB
+    pub custom_bands: Vec<ParametricBand>,
+}
+
+impl Default for EqualizerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preset: Some("Flat".to_string()),
+            preamp_db: 0.0,
+            bands: [0.0; 10],
+            custom_bands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EqualizerPreset {
+    pub name: String,
+    pub bands: [f32; 10],
+}
+
+/// Second-order IIR section in direct form I, coefficients normalized by a0.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    pub(crate) fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [1.0, a[1] / a[0], a[2] / a[0]],
+            ..Default::default()
+        }
+    }
+
+    /// RBJ Audio EQ Cookbook filters.
+    fn design(kind: FilterKind, frequency: f64, gain_db: f64, q: f64, sample_rate: f64) -> Self {
+        let amp = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+
+        match kind {
+            FilterKind::Peaking => Self::new(
+                [1.0 + alpha * amp, -2.0 * cos, 1.0 - alpha * amp],
+                [1.0 + alpha / amp, -2.0 * cos, 1.0 - alpha / amp],
+            ),
+            FilterKind::LowShelf => {
+                let beta = 2.0 * amp.sqrt() * alpha;
+                Self::new(
+                    [
+                        amp * ((amp + 1.0) - (amp - 1.0) * cos + beta),
+                        2.0 * amp * ((amp - 1.0) - (amp + 1.0) * cos),
+                        amp * ((amp + 1.0) - (amp - 1.0) * cos - beta),
+                    ],
+                    [
+                        (amp + 1.0) + (amp - 1.0) * cos + beta,
+                        -2.0 * ((amp - 1.0) + (amp + 1.0) * cos),
+                        (amp + 1.0) + (amp - 1.0) * cos - beta,
+                    ],
+                )
+            },
+            FilterKind::HighShelf => {
+                let beta = 2.0 * amp.sqrt() * alpha;
+                Self::new(
+                    [
+                        amp * ((amp + 1.0) + (amp - 1.0) * cos + beta),
+                        -2.0 * amp * ((amp - 1.0) + (amp + 1.0) * cos),
+                        amp * ((amp + 1.0) + (amp - 1.0) * cos - beta),
+                    ],
+                    [
+                        (amp + 1.0) - (amp - 1.0) * cos + beta,
+                        2.0 * ((amp - 1.0) - (amp + 1.0) * cos),
+                        (amp + 1.0) - (amp - 1.0) * cos - beta,
+                    ],
+                )
+            },
+        }
+    }
+
+    pub(crate) fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[1] * self.y[0]
+            - self.a[2] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+
+    /// Swaps in new coefficients but keeps the filter memory, so live edits don't click.
+    fn retune(&mut self, other: &Biquad) {
+        self.b = other.b;
+        self.a = other.a;
+    }
+}
+
+impl EqualizerSettings {
+    /// Filters for one channel. Flat bands stay in the chain (they are an identity filter)
+    /// so that edits keep the same chain and only retune it.
+    fn design(&self, sample_rate: f64) -> Vec<Biquad> {
+        let nyquist = sample_rate / 2.0;
+        let graphic = GRAPHIC_FREQUENCIES
+            .iter()
+            .zip(self.bands.iter())
+            .map(|(f, g)| (FilterKind::Peaking, *f as f64, *g as f64, GRAPHIC_Q));
+        let custom = self
+            .custom_bands
+            .iter()
+            .map(|b| (b.kind, b.frequency as f64, b.gain_db as f64, b.q.max(0.1) as f64));
+
+        graphic
+            .chain(custom)
+            .filter(|(_, f, _, _)| *f > 0.0 && *f < nyquist)
+            .map(|(kind, f, g, q)| {
+                let g = g.clamp(-(MAX_GAIN_DB as f64), MAX_GAIN_DB as f64);
+                Biquad::design(kind, f, g, q, sample_rate)
+            })
+            .collect()
+    }
+}
+
+/// EQ settings shared between the audio thread and every playing `Equalizer` source.
+#[derive(Clone)]
+pub struct EqualizerHandle {
+    settings: Arc<Mutex<EqualizerSettings>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl EqualizerHandle {
+    pub fn new(settings: EqualizerSettings) -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(settings)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn set(&self, settings: EqualizerSettings) {
+        if let Ok(mut current) = self.settings.lock() {
+            *current = settings;
+            self.generation.fetch_add(1, Ordering::Release);
+        }
+    }
+}
+
+pub struct Equalizer<S> {
+    input: S,
+    handle: EqualizerHandle,
+    generation: u64,
+    enabled: bool,
+    preamp: f64,
+    // One filter chain per channel
+    filters: Vec<Vec<Biquad>>,
+    channel: usize,
+    until_check: usize,
+}
+
+impl<S: Source> Equalizer<S> {
+    pub fn new(input: S, handle: &EqualizerHandle) -> Self {
+        let mut eq = Self {
+            input,
+            handle: handle.clone(),
+            generation: u64::MAX,
+            enabled: false,
+            preamp: 1.0,
+            filters: Vec::new(),
+            channel: 0,
+            until_check: 0,
+        };
+        eq.refresh();
+        eq
+    }
+
+    fn refresh(&mut self) {
+        let generation = self.handle.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        // Never block the audio callback, retry on the next check instead
+        let settings = match self.handle.settings.try_lock() {
+            Ok(settings) => settings.clone(),
+            Err(_) => return,
+        };
+        self.generation = generation;
+        self.enabled = settings.enabled;
+        self.preamp = 10f64.powf(settings.preamp_db as f64 / 20.0);
+
+        let design = settings.design(self.input.sample_rate() as f64);
+        let channels = self.input.channels().max(1) as usize;
+        let keep_state = self.filters.len() == channels
+            && self.filters.iter().all(|chain| chain.len() == design.len());
+        if keep_state {
+            for chain in &mut self.filters {
+                for (filter, new) in chain.iter_mut().zip(&design) {
+                    filter.retune(new);
+                }
+            }
+        } else {
+            self.filters = vec![design; channels];
+        }
+        self.channel = self.channel.min(channels - 1);
+    }
+}
+
+impl<S: Source> Iterator for Equalizer<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.until_check == 0 {
+            self.refresh();
+            self.until_check = CHECK_INTERVAL;
+        }
+        self.until_check -= 1;
+
+        let sample = self.input.next()?;
+        if !self.enabled || self.filters.is_empty() {
+            return Some(sample);
+        }
+
+        let channels = self.filters.len();
+        let chain = &mut self.filters[self.channel];
+        self.channel = (self.channel + 1) % channels;
+        let output = chain
+            .iter_mut()
+            .fold(sample as f64 * self.preamp, |acc, filter| filter.process(acc));
+        Some(output as Sample)
+    }
+}
+
+impl<S: Source> Source for Equalizer<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+pub fn preset(name: &str) -> Option<[f32; 10]> {
+    PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+        .map(|(_, bands)| *bands)
+}
+
+/// Applies the EQ to the playing track and stores it in the settings file.
+fn update_equalizer(
+    player: &AudioPlayer,
+    change: impl FnOnce(&mut EqualizerSettings) -> Result<(), String>,
+) -> Result<EqualizerSettings, String> {
+    let mut settings = load_settings()?;
+    change(&mut settings.equalizer)?;
+    player.set_equalizer(settings.equalizer.clone())?;
+    write_settings(&settings)?;
+    Ok(settings.equalizer)
+}
+
+#[tauri::command]
+pub fn get_equalizer_presets() -> Vec<EqualizerPreset> {
+    PRESETS
+        .iter()
+        .map(|(name, bands)| EqualizerPreset {
+            name: name.to_string(),
+            bands: *bands,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn set_equalizer(player: State<'_, AudioPlayer>, equalizer: EqualizerSettings) -> Result<EqualizerSettings, String> {
+    update_equalizer(&player, |eq| {
+        *eq = equalizer;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn set_equalizer_band(player: State<'_, AudioPlayer>, index: usize, gain_db: f32) -> Result<EqualizerSettings, String> {
+    update_equalizer(&player, |eq| {
+        let band = eq.bands.get_mut(index).ok_or("Band index out of range")?;
+        *band = gain_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        eq.preset = None;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn apply_equalizer_preset(player: State<'_, AudioPlayer>, name: String) -> Result<EqualizerSettings, String> {
+    update_equalizer(&player, |eq| {
+        eq.bands = preset(&name).ok_or("Unknown preset")?;
+        eq.preset = Some(name);
+        eq.enabled = true;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    const RATE: u32 = 48000;
+
+    fn sine(frequency: f32, seconds: f32) -> Vec<f32> {
+        let len = (RATE as f32 * seconds) as usize;
+        (0..len)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// Gain in dB the EQ applies to a sine of `frequency`, ignoring the filter settle time.
+    fn response_db(settings: EqualizerSettings, frequency: f32) -> f32 {
+        let input = sine(frequency, 1.0);
+        let handle = EqualizerHandle::new(settings);
+        let source = Equalizer::new(SamplesBuffer::new(1, RATE, input.clone()), &handle);
+        let output: Vec<f32> = source.collect();
+        let settled = RATE as usize / 4;
+        20.0 * (rms(&output[settled..]) / rms(&input[settled..])).log10()
+    }
+
+    fn with_band(index: usize, gain_db: f32) -> EqualizerSettings {
+        let mut settings = EqualizerSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        settings.bands[index] = gain_db;
+        settings
+    }
+
+    #[test]
+    fn boosts_band_center() {
+        let gain = response_db(with_band(5, 6.0), 1000.0);
+        assert!((gain - 6.0).abs() < 0.5, "gain at 1 kHz was {gain} dB");
+    }
+
+    #[test]
+    fn cuts_band_center() {
+        let gain = response_db(with_band(3, -9.0), 250.0);
+        assert!((gain + 9.0).abs() < 0.5, "gain at 250 Hz was {gain} dB");
+    }
+
+    #[test]
+    fn leaves_distant_frequencies_alone() {
+        let gain = response_db(with_band(1, 12.0), 8000.0);
+        assert!(gain.abs() < 0.5, "gain at 8 kHz was {gain} dB");
+    }
+
+    #[test]
+    fn disabled_is_bit_exact() {
+        let mut settings = with_band(5, 6.0);
+        settings.enabled = false;
+        let input = sine(1000.0, 0.1);
+        let handle = EqualizerHandle::new(settings);
+        let output: Vec<f32> = Equalizer::new(SamplesBuffer::new(1, RATE, input.clone()), &handle).collect();
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    fn preamp_scales_flat_response() {
+        let settings = EqualizerSettings {
+            enabled: true,
+            preamp_db: -6.0,
+            ..Default::default()
+        };
+        let gain = response_db(settings, 440.0);
+        assert!((gain + 6.0).abs() < 0.1, "gain with -6 dB preamp was {gain} dB");
+    }
+
+    #[test]
+    fn parametric_high_shelf_lifts_treble() {
+        let settings = EqualizerSettings {
+            enabled: true,
+            custom_bands: vec![ParametricBand {
+                kind: FilterKind::HighShelf,
+                frequency: 2000.0,
+                gain_db: 6.0,
+                q: 0.707,
+            }],
+            ..Default::default()
+        };
+        let treble = response_db(settings.clone(), 12000.0);
+        let bass = response_db(settings, 100.0);
+        assert!((treble - 6.0).abs() < 0.5, "gain at 12 kHz was {treble} dB");
+        assert!(bass.abs() < 0.5, "gain at 100 Hz was {bass} dB");
+    }
+
+    #[test]
+    fn live_update_changes_response() {
+        let handle = EqualizerHandle::new(EqualizerSettings::default());
+        let input = sine(1000.0, 1.0);
+        let mut source = Equalizer::new(SamplesBuffer::new(1, RATE, input.clone()), &handle);
+        let before: Vec<f32> = source.by_ref().take(RATE as usize / 2).collect();
+        handle.set(with_band(5, 6.0));
+        let after: Vec<f32> = source.collect();
+
+        let half = RATE as usize / 2;
+        assert!((rms(&before) / rms(&input[..half]) - 1.0).abs() < 1e-6);
+        let settled = &after[RATE as usize / 8..];
+        let gain = 20.0 * (rms(settled) / rms(&input[half + RATE as usize / 8..])).log10();
+        assert!((gain - 6.0).abs() < 0.5, "gain after update was {gain} dB");
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+
+// Number of samples between checks for a new ramp, keeps locking off the per-sample path
+const CHECK_INTERVAL: usize = 256;
+
+#[derive(Clone, Copy)]
+struct Ramp {
+    target: f32,
+    duration: Duration,
+}
+
+/// Handle used by the audio thread to ramp the gain of a `Fade` source.
+#[derive(Clone)]
+pub struct FadeHandle {
+    pending: Arc<Mutex<Option<Ramp>>>,
+    initial: f32,
+}
+
+impl FadeHandle {
+    pub fn new(initial: f32) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(None)),
+            initial,
+        }
+    }
+
+    /// Moves the gain linearly from wherever it is now to `target` over `duration`.
+    pub fn ramp_to(&self, target: f32, duration: Duration) {
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some(Ramp { target, duration });
+        }
+    }
+}
+
+/// Applies a gain that can be ramped up or down while the source is playing.
+pub struct Fade<S> {
+    input: S,
+    pending: Arc<Mutex<Option<Ramp>>>,
+    gain: f32,
+    target: f32,
+    step: f32,
+    until_check: usize,
+}
+
+impl<S: Source> Fade<S> {
+    pub fn new(input: S, handle: &FadeHandle) -> Self {
+        Self {
+            input,
+            pending: handle.pending.clone(),
+            gain: handle.initial,
+            target: handle.initial,
+            step: 0.0,
+            until_check: 0,
+        }
+    }
+
+    fn check_pending(&mut self) {
+        let ramp = match self.pending.try_lock() {
+            Ok(mut pending) => pending.take(),
+            Err(_) => None,
+        };
+        if let Some(ramp) = ramp {
+            let samples = ramp.duration.as_secs_f32()
+                * self.input.sample_rate() as f32
+                * self.input.channels() as f32;
+            self.target = ramp.target;
+            self.step = if samples >= 1.0 {
+                (ramp.target - self.gain) / samples
+            } else {
+                ramp.target - self.gain
+            };
+        }
+    }
+}
+
+impl<S: Source> Iterator for Fade<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.until_check == 0 {
+            self.check_pending();
+            self.until_check = CHECK_INTERVAL;
+        }
+        self.until_check -= 1;
+
+        let sample = self.input.next()?;
+        if self.gain != self.target {
+            self.gain += self.step;
+            if (self.step >= 0.0 && self.gain >= self.target) || (self.step < 0.0 && self.gain <= self.target) {
+                self.gain = self.target;
+            }
+        }
+        Some(sample * self.gain)
+    }
+}
+
+impl<S: Source> Source for Fade<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
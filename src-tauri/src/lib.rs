@@ -1,3 +1,5 @@
+use tauri::Manager;
+
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -5,11 +7,26 @@ fn greet(name: &str) -> String {
 }
 
 pub mod commands;
+pub mod database;
+pub mod equalizer;
+pub mod library;
+pub mod fade;
 pub mod player;
 pub mod playlist;
+pub mod queue;
+pub mod replaygain;
+pub mod search;
+pub mod session;
 pub mod settings;
+pub mod silence;
+pub mod speed;
+pub mod stereo;
+pub mod visualizer;
+pub mod waveform;
+pub mod watcher;
 pub mod lyrics;
 pub mod analytics;
+pub mod loudness;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -17,11 +34,18 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_process::init())
-        .manage(player::AudioPlayer::new())
+        .setup(|app| {
+            app.manage(player::AudioPlayer::new(app.handle().clone()));
+            app.manage(watcher::LibraryWatcher::new(app.handle().clone()));
+            std::thread::spawn(search::warm_up);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet, 
             commands::scan_music_dir,
             commands::get_cached_library,
+            library::query_library,
+            search::search_library,
             commands::get_song_art,
             commands::get_thumbnail,
             commands::clear_cache,
@@ -30,7 +54,35 @@ pub fn run() {
             commands::toggle_playback,
             commands::stop_playback,
             commands::seek_track,
+            commands::seek_track_ms,
+            commands::seek_relative,
+            commands::seek_forward,
+            commands::seek_backward,
+            commands::set_ab_loop,
+            commands::clear_ab_loop,
             commands::set_player_volume,
+            commands::set_queue,
+            commands::next_track,
+            commands::previous_track,
+            commands::jump_to_queue_index,
+            commands::insert_next_tracks,
+            commands::append_tracks,
+            commands::move_queue_track,
+            commands::remove_queue_track,
+            commands::clear_play_queue,
+            commands::set_shuffle,
+            commands::set_repeat_mode,
+            commands::get_queue,
+            commands::get_player_state,
+            commands::set_playback_speed,
+            commands::set_stereo,
+            commands::set_visualizer,
+            commands::set_sleep_timer,
+            commands::set_sleep_timer_tracks,
+            commands::cancel_sleep_timer,
+            commands::get_sleep_timer,
+            commands::get_output_devices,
+            commands::set_output_device,
             playlist::create_playlist,
             playlist::get_playlists,
             playlist::add_to_playlist,
@@ -38,14 +90,28 @@ pub fn run() {
             playlist::delete_playlist,
             settings::load_settings,
             settings::save_settings,
+            equalizer::get_equalizer_presets,
+            equalizer::set_equalizer,
+            equalizer::set_equalizer_band,
+            equalizer::apply_equalizer_preset,
             lyrics::get_song_lyrics,
             lyrics::save_song_lyrics,
             lyrics::delete_song_lyrics,
             lyrics::fetch_lyrics_online,
             analytics::increment_play_count,
             analytics::get_play_stats,
+            loudness::analyze_loudness,
+            waveform::get_waveform,
             commands::update_song_metadata
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Covers quitting from the sleep timer too, not just closing the window
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = app.state::<player::AudioPlayer>().save_session() {
+                    eprintln!("Failed to save session: {}", e);
+                }
+            }
+        });
 }
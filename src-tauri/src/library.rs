@@ -0,0 +1,203 @@
+use serde::{Serialize, Deserialize};
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+use crate::commands::Song;
+use crate::database::{self, song_from_row, SONG_SELECT};
+
+// Larger requests are cut down to this many songs
+pub const MAX_PAGE_SIZE: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Year,
+    TrackNumber,
+    Duration,
+    Bitrate,
+    SampleRate,
+    FileSize,
+    DateAdded,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SortKey {
+    pub field: SortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+/// Every field is optional, an empty filter matches the whole library.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LibraryFilter {
+    pub artist: Option<String>, // exact match, ignoring case
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year_min: Option<u32>,
+    pub year_max: Option<u32>,
+    pub formats: Vec<String>, // file extensions like "flac", any of them matches
+    pub bitrate_min: Option<u32>, // kbps
+    pub bitrate_max: Option<u32>,
+    pub has_lyrics: Option<bool>, // embedded or saved
+    pub has_art: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibraryPage {
+    pub songs: Vec<Song>,
+    pub total: u64, // songs matching the filter across all pages
+    pub offset: u64,
+}
+
+impl SortField {
+    fn column(self) -> &'static str {
+        match self {
+            SortField::Title => "s.title COLLATE NOCASE",
+            SortField::Artist => "ar.name COLLATE NOCASE",
+            SortField::Album => "al.title COLLATE NOCASE",
+            SortField::Genre => "s.genre COLLATE NOCASE",
+            SortField::Year => "s.year",
+            SortField::TrackNumber => "s.track_number",
+            SortField::Duration => "s.duration_seconds",
+            SortField::Bitrate => "s.bitrate",
+            SortField::SampleRate => "s.sample_rate",
+            SortField::FileSize => "s.file_size_bytes",
+            SortField::DateAdded => "s.id",
+        }
+    }
+}
+
+impl LibraryFilter {
+    /// The WHERE clause for this filter, with its parameters in order.
+    fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut values = Vec::new();
+        let mut push = |condition: &str, value: Value| {
+            values.push(value);
+            conditions.push(condition.replace('?', &format!("?{}", values.len())));
+        };
+
+        if let Some(artist) = &self.artist {
+            push("ar.name = ? COLLATE NOCASE", Value::Text(artist.clone()));
+        }
+        if let Some(album) = &self.album {
+            push("al.title = ? COLLATE NOCASE", Value::Text(album.clone()));
+        }
+        if let Some(genre) = &self.genre {
+            push("s.genre = ? COLLATE NOCASE", Value::Text(genre.clone()));
+        }
+        if let Some(year) = self.year_min {
+            push("s.year >= ?", Value::Integer(year as i64));
+        }
+        if let Some(year) = self.year_max {
+            push("s.year <= ?", Value::Integer(year as i64));
+        }
+        if let Some(bitrate) = self.bitrate_min {
+            push("s.bitrate >= ?", Value::Integer(bitrate as i64));
+        }
+        if let Some(bitrate) = self.bitrate_max {
+            push("s.bitrate <= ?", Value::Integer(bitrate as i64));
+        }
+        if !self.formats.is_empty() {
+            // LIKE ignores ASCII case, so "FLAC" and "flac" files both match. Wildcards in
+            // the extension itself are escaped, so "m_a" can't match ".m4a".
+            let formats: Vec<String> = self
+                .formats
+                .iter()
+                .map(|format| {
+                    values.push(Value::Text(format!("%.{}", escape_like(format.trim_start_matches('.')))));
+                    format!("s.path LIKE ?{} ESCAPE '\\'", values.len())
+                })
+                .collect();
+            conditions.push(format!("({})", formats.join(" OR ")));
+        }
+        if let Some(has_lyrics) = self.has_lyrics {
+            let lyrics = "(COALESCE(s.lyrics, '') != '' OR EXISTS (SELECT 1 FROM lyrics l WHERE l.path = s.path))";
+            conditions.push(if has_lyrics { lyrics.to_string() } else { format!("NOT {}", lyrics) });
+        }
+        if let Some(has_art) = self.has_art {
+            conditions.push(format!("s.has_album_art = {}", has_art as u8));
+        }
+
+        if conditions.is_empty() {
+            (String::new(), values)
+        } else {
+            (format!(" WHERE {}", conditions.join(" AND ")), values)
+        }
+    }
+}
+
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn order_by(sort: &[SortKey]) -> String {
+    let mut keys: Vec<String> = sort
+        .iter()
+        .map(|key| {
+            let direction = match key.direction {
+                SortDirection::Asc => "ASC",
+                SortDirection::Desc => "DESC",
+            };
+            // Songs missing the field go last whichever way the list is sorted
+            format!("{} {} NULLS LAST", key.field.column(), direction)
+        })
+        .collect();
+    // Keeps the order stable between pages when the keys tie
+    keys.push("s.id".to_string());
+    format!(" ORDER BY {}", keys.join(", "))
+}
+
+/// One page of the library matching `filter`, sorted by `sort` in priority order.
+/// Without sort keys songs come in the order they were added.
+#[tauri::command]
+pub fn query_library(filter: LibraryFilter, sort: Vec<SortKey>, offset: u64, limit: u64) -> Result<LibraryPage, String> {
+    let (conditions, mut values) = filter.to_sql();
+    let limit = limit.min(MAX_PAGE_SIZE);
+
+    database::with_db(|conn| {
+        let count_sql = format!(
+            "SELECT COUNT(*) FROM songs s
+             LEFT JOIN artists ar ON ar.id = s.artist_id
+             LEFT JOIN albums al ON al.id = s.album_id{}",
+            conditions
+        );
+        let total: u64 = conn.query_row(&count_sql, params_from_iter(values.iter()), |row| row.get(0))?;
+
+        values.push(Value::Integer(limit as i64));
+        values.push(Value::Integer(offset.min(i64::MAX as u64) as i64));
+        let page_sql = format!(
+            "{}{}{} LIMIT ?{} OFFSET ?{}",
+            SONG_SELECT,
+            conditions,
+            order_by(&sort),
+            values.len() - 1,
+            values.len()
+        );
+        let mut stmt = conn.prepare(&page_sql)?;
+        let songs = stmt
+            .query_map(params_from_iter(values.iter()), song_from_row)?
+            .collect::<rusqlite::Result<Vec<Song>>>()?;
+
+        Ok(LibraryPage { songs, total, offset })
+    })
+}
@@ -0,0 +1,377 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+use lofty::prelude::*;
+use lofty::read_from_path;
+use lofty::config::WriteOptions;
+use lofty::tag::ItemKey;
+use rayon::prelude::*;
+use rodio::Source;
+use rusqlite::{params, Transaction};
+use tauri::Emitter;
+use crate::commands::{get_cached_library, ProgressPayload, Song};
+use crate::database;
+use crate::equalizer::Biquad;
+use crate::player::open_source;
+use crate::replaygain::ReplayGain;
+
+// ReplayGain 2.0 reference level
+const REFERENCE_LUFS: f64 = -18.0;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+// Oversampling factor and taps per phase of the true-peak interpolator
+const OVERSAMPLE: usize = 4;
+const PEAK_TAPS: usize = 12;
+
+/// Analysis result for one file, stored in the `loudness` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoudnessInfo {
+    pub integrated_lufs: f64,
+    pub true_peak: f64, // linear, 1.0 = full scale
+    pub album_lufs: Option<f64>,
+    pub album_peak: Option<f64>,
+    pub modified: u64, // file mtime, used to detect stale entries
+}
+
+impl LoudnessInfo {
+    pub fn replaygain(&self) -> ReplayGain {
+        ReplayGain {
+            track_gain: Some((REFERENCE_LUFS - self.integrated_lufs) as f32),
+            track_peak: Some(self.true_peak as f32),
+            album_gain: self.album_lufs.map(|l| (REFERENCE_LUFS - l) as f32),
+            album_peak: self.album_peak.map(|p| p as f32),
+        }
+    }
+}
+
+pub(crate) fn insert(tx: &Transaction, path: &str, info: &LoudnessInfo) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT OR REPLACE INTO loudness (path, integrated_lufs, true_peak, album_lufs, album_peak, modified)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![path, info.integrated_lufs, info.true_peak, info.album_lufs, info.album_peak, info.modified],
+    )?;
+    Ok(())
+}
+
+/// When each analyzed file was last modified at the time it was measured.
+fn analyzed_modified() -> Result<HashMap<String, u64>, String> {
+    database::with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT path, modified FROM loudness")?;
+        let modified = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+        modified
+    })
+}
+
+/// Forgets every measurement, the next analysis starts over.
+pub(crate) fn clear() -> Result<(), String> {
+    database::with_db(|conn| conn.execute("DELETE FROM loudness", []).map(|_| ()))
+}
+
+pub(crate) fn modified_secs(path: &str) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The two BS.1770 K-weighting stages (high shelf + high pass) for a sample rate.
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [vh + vb * k / q + k * k, 2.0 * (k * k - vh), vh - vb * k / q + k * k],
+        [a0, 2.0 * (k * k - 1.0), 1.0 - k / q + k * k],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// BS.1770 channel weights, assuming the usual L R C LFE Ls Rs order.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6.., 3) => 0.0,
+        (6.., 4) | (6.., 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Windowed-sinc coefficients for each phase of the oversampling interpolator.
+fn interpolation_phases() -> Vec<[f64; PEAK_TAPS]> {
+    let length = OVERSAMPLE * PEAK_TAPS;
+    let center = (length - 1) as f64 / 2.0;
+    (0..OVERSAMPLE)
+        .map(|phase| {
+            let mut taps = [0.0; PEAK_TAPS];
+            for (j, tap) in taps.iter_mut().enumerate() {
+                let n = (j * OVERSAMPLE + phase) as f64;
+                let x = (n - center) / OVERSAMPLE as f64;
+                let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let window = 0.5 - 0.5 * (2.0 * PI * (n + 0.5) / length as f64).cos();
+                *tap = sinc * window;
+            }
+            taps
+        })
+        .collect()
+}
+
+/// Mean-square power of every 400 ms gating block plus the true peak of one track.
+struct TrackAnalysis {
+    blocks: Vec<f64>,
+    true_peak: f64,
+}
+
+fn analyze_file(path: &str) -> Result<TrackAnalysis, String> {
+    let source = open_source(path)?;
+    let channels = source.channels() as usize;
+    let sample_rate = source.sample_rate() as f64;
+    if channels == 0 || sample_rate <= 0.0 {
+        return Err(format!("Unsupported stream in {}", path));
+    }
+
+    let mut filters: Vec<[Biquad; 2]> = (0..channels).map(|_| k_weighting(sample_rate)).collect();
+    let weights: Vec<f64> = (0..channels).map(|c| channel_weight(c, channels)).collect();
+    let phases = interpolation_phases();
+    let mut history = vec![[0.0f64; PEAK_TAPS]; channels];
+
+    // Gating blocks are 400 ms long and start every 100 ms, so sum power per 100 ms step
+    let step_frames = (sample_rate / 10.0).round() as usize;
+    let mut step_power = 0.0;
+    let mut step_fill = 0;
+    let mut recent_steps: Vec<f64> = Vec::with_capacity(4);
+    let mut blocks = Vec::new();
+    let mut true_peak: f64 = 0.0;
+
+    let mut channel = 0;
+    for sample in source {
+        let sample = sample as f64;
+
+        // True peak: the sample itself plus interpolated points between it and its predecessors
+        let taps = &mut history[channel];
+        taps.copy_within(0..PEAK_TAPS - 1, 1);
+        taps[0] = sample;
+        true_peak = true_peak.max(sample.abs());
+        for phase in &phases {
+            let value: f64 = phase.iter().zip(taps.iter()).map(|(c, x)| c * x).sum();
+            true_peak = true_peak.max(value.abs());
+        }
+
+        let [shelf, high_pass] = &mut filters[channel];
+        let weighted = high_pass.process(shelf.process(sample));
+        step_power += weights[channel] * weighted * weighted;
+
+        channel += 1;
+        if channel == channels {
+            channel = 0;
+            step_fill += 1;
+            if step_fill == step_frames {
+                if recent_steps.len() == 4 {
+                    recent_steps.remove(0);
+                }
+                recent_steps.push(step_power);
+                if recent_steps.len() == 4 {
+                    blocks.push(recent_steps.iter().sum::<f64>() / (4 * step_frames) as f64);
+                }
+                step_power = 0.0;
+                step_fill = 0;
+            }
+        }
+    }
+
+    Ok(TrackAnalysis { blocks, true_peak })
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn lufs_to_power(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+/// Gated integrated loudness over a set of blocks, `None` for silence.
+fn integrated_loudness<'a>(blocks: impl Iterator<Item = &'a f64> + Clone) -> Option<f64> {
+    let absolute = lufs_to_power(ABSOLUTE_GATE_LUFS);
+    let mean = |threshold: f64| {
+        let (sum, count) = blocks
+            .clone()
+            .filter(|p| **p > threshold)
+            .fold((0.0, 0usize), |(sum, count), p| (sum + p, count + 1));
+        (count > 0).then(|| sum / count as f64)
+    };
+
+    let ungated = mean(absolute)?;
+    let relative = lufs_to_power(power_to_lufs(ungated) + RELATIVE_GATE_LU);
+    mean(absolute.max(relative)).map(power_to_lufs)
+}
+
+fn write_replaygain_tags(path: &str, gain: &ReplayGain) -> Result<(), String> {
+    let path_buf = PathBuf::from(path);
+    let mut tagged_file = read_from_path(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let tag = match tagged_file.primary_tag_mut() {
+        Some(t) => t,
+        None => {
+            let tag_type = tagged_file.file_type().primary_tag_type();
+            tagged_file.insert_tag(lofty::tag::Tag::new(tag_type));
+            tagged_file.primary_tag_mut().unwrap()
+        }
+    };
+
+    let items = [
+        (ItemKey::ReplayGainTrackGain, gain.track_gain.map(|g| format!("{:.2} dB", g))),
+        (ItemKey::ReplayGainTrackPeak, gain.track_peak.map(|p| format!("{:.6}", p))),
+        (ItemKey::ReplayGainAlbumGain, gain.album_gain.map(|g| format!("{:.2} dB", g))),
+        (ItemKey::ReplayGainAlbumPeak, gain.album_peak.map(|p| format!("{:.6}", p))),
+    ];
+    for (key, value) in items {
+        if let Some(value) = value {
+            tag.insert_text(key, value);
+        }
+    }
+
+    tagged_file
+        .save_to_path(&path_buf, WriteOptions::default())
+        .map_err(|e| format!("Failed to save metadata: {}", e))
+}
+
+/// Tracks are grouped per album within a folder so two "Greatest Hits" don't get merged.
+fn album_key(song: &Song) -> Option<(String, PathBuf)> {
+    let album = song.album.clone()?;
+    let dir = Path::new(&song.path).parent()?.to_path_buf();
+    Some((album, dir))
+}
+
+/// Measures every library track without ReplayGain tags whose analysis is missing or stale.
+/// Albums are always analyzed as a whole so the album loudness stays consistent.
+#[tauri::command]
+pub async fn analyze_loudness(app: tauri::AppHandle, write_tags: bool) -> Result<usize, String> {
+    let songs = get_cached_library()?;
+    let analyzed_at = analyzed_modified()?;
+
+    let untagged: Vec<&Song> = songs
+        .iter()
+        .filter(|s| s.replaygain_track_gain.is_none() && s.replaygain_album_gain.is_none())
+        .collect();
+    let is_stale = |song: &Song| {
+        analyzed_at
+            .get(&song.path)
+            .is_none_or(|modified| *modified != modified_secs(&song.path))
+    };
+
+    let stale_albums: Vec<(String, PathBuf)> = untagged
+        .iter()
+        .filter(|s| is_stale(s))
+        .filter_map(|s| album_key(s))
+        .collect();
+    let targets: Vec<&Song> = untagged
+        .into_iter()
+        .filter(|s| is_stale(s) || album_key(s).is_some_and(|k| stale_albums.contains(&k)))
+        .collect();
+
+    let total = targets.len();
+    let processed = Arc::new(AtomicUsize::new(0));
+    let app_clone = app.clone();
+
+    let results: Vec<(&Song, TrackAnalysis)> = targets
+        .par_iter()
+        .filter_map(|song| {
+            let result = analyze_file(&song.path);
+
+            let count = processed.fetch_add(1, Ordering::SeqCst) + 1;
+            let _ = app_clone.emit("loudness-progress", ProgressPayload {
+                current: count,
+                total,
+            });
+
+            match result {
+                Ok(analysis) => Some((*song, analysis)),
+                Err(e) => {
+                    eprintln!("Loudness analysis failed for {}: {}", song.path, e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let mut albums: HashMap<(String, PathBuf), (Vec<f64>, f64)> = HashMap::new();
+    for (song, analysis) in &results {
+        if let Some(key) = album_key(song) {
+            let entry = albums.entry(key).or_insert_with(|| (Vec::new(), 0.0));
+            entry.0.extend_from_slice(&analysis.blocks);
+            entry.1 = entry.1.max(analysis.true_peak);
+        }
+    }
+
+    let mut measured = Vec::new();
+    for (song, analysis) in &results {
+        let integrated_lufs = match integrated_loudness(analysis.blocks.iter()) {
+            Some(lufs) => lufs,
+            None => continue, // Digital silence has no meaningful loudness
+        };
+        let album = album_key(song).and_then(|k| albums.get(&k));
+
+        let info = LoudnessInfo {
+            integrated_lufs,
+            true_peak: analysis.true_peak,
+            album_lufs: album.and_then(|(blocks, _)| integrated_loudness(blocks.iter())),
+            album_peak: album.map(|(_, peak)| *peak),
+            modified: modified_secs(&song.path),
+        };
+
+        let mut tagged = false;
+        if write_tags {
+            match write_replaygain_tags(&song.path, &info.replaygain()) {
+                Ok(()) => tagged = true,
+                Err(e) => eprintln!("Failed to write ReplayGain tags for {}: {}", song.path, e),
+            }
+        }
+
+        // Writing tags changes the mtime, so record it afterwards
+        let info = LoudnessInfo {
+            modified: modified_secs(&song.path),
+            ..info
+        };
+        measured.push((song.path.as_str(), info, tagged));
+    }
+
+    database::with_db(|conn| {
+        let tx = conn.transaction()?;
+        for (path, info, tagged) in &measured {
+            insert(&tx, path, info)?;
+            if *tagged {
+                // The library entry must match the retagged file, or the measurement
+                // above no longer counts for it
+                let gain = info.replaygain();
+                let size = fs::metadata(path).map_or(0, |m| m.len());
+                tx.execute(
+                    "UPDATE songs SET modified_secs = ?2, file_size_bytes = ?3, replaygain_track_gain = ?4,
+                     replaygain_track_peak = ?5, replaygain_album_gain = ?6, replaygain_album_peak = ?7
+                     WHERE path = ?1",
+                    params![path, info.modified, size, gain.track_gain, gain.track_peak, gain.album_gain, gain.album_peak],
+                )?;
+            }
+        }
+        tx.commit()
+    })?;
+    Ok(measured.len())
+}
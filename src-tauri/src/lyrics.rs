@@ -1,61 +1,47 @@
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-use std::io::Write;
+use rusqlite::{params, OptionalExtension};
+use crate::database;
+use crate::search;
 
-fn get_lyrics_path() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap_or(PathBuf::from("."));
-    path.push("luma");
-    let _ = fs::create_dir_all(&path);
-    path.push("lyrics.json");
-    path
-}
-
-fn load_lyrics_store() -> HashMap<String, String> {
-    let path = get_lyrics_path();
-    if !path.exists() {
-        return HashMap::new();
-    }
-    
-    match fs::read_to_string(&path) {
-        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
-        Err(_) => HashMap::new(),
-    }
-}
-
-fn save_lyrics_store(store: &HashMap<String, String>) -> Result<(), String> {
-    let path = get_lyrics_path();
-    let json = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
-    let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-    file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
-    Ok(())
+/// Every saved lyric by song path.
+pub(crate) fn load_saved_lyrics() -> Result<HashMap<String, String>, String> {
+    database::with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT path, lyrics FROM lyrics")?;
+        let lyrics = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+        lyrics
+    })
 }
 
 #[tauri::command]
 pub fn get_song_lyrics(song_path: String) -> Result<Option<String>, String> {
-    let store = load_lyrics_store();
-    Ok(store.get(&song_path).cloned())
+    database::with_db(|conn| {
+        conn.query_row("SELECT lyrics FROM lyrics WHERE path = ?1", [&song_path], |row| row.get(0))
+            .optional()
+    })
 }
 
 #[tauri::command]
 pub fn save_song_lyrics(song_path: String, lyrics: String) -> Result<(), String> {
-    let mut store = load_lyrics_store();
-    
     if lyrics.trim().is_empty() {
-        store.remove(&song_path);
-    } else {
-        store.insert(song_path, lyrics);
+        return delete_song_lyrics(song_path);
     }
-    
-    save_lyrics_store(&store)
+
+    database::with_db(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO lyrics (path, lyrics) VALUES (?1, ?2)",
+            params![song_path, lyrics],
+        )
+    })?;
+    search::update(&[song_path]);
+    Ok(())
 }
 
 #[tauri::command]
 pub fn delete_song_lyrics(song_path: String) -> Result<(), String> {
-    let mut store = load_lyrics_store();
-    store.remove(&song_path);
-    save_lyrics_store(&store)
+    database::with_db(|conn| conn.execute("DELETE FROM lyrics WHERE path = ?1", [&song_path]))?;
+    search::update(&[song_path]);
+    Ok(())
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,154 +1,1592 @@
 use std::thread;
-use std::sync::{mpsc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
 use std::fs::File;
 use std::io::BufReader;
-use std::time::Duration;
-use rodio::{Decoder, Source, OutputStreamBuilder, Sink};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+use tauri::{AppHandle, Emitter};
+use rodio::{Decoder, Source, OutputStream, OutputStreamBuilder, Sink, Sample, ChannelCount, SampleRate};
+use rodio::cpal::{self, traits::{DeviceTrait, HostTrait}};
+use rodio::mixer::{self, Mixer};
+use rodio::source::SeekError;
+use crate::commands::mark_unplayable;
+use crate::database;
+use crate::equalizer::{Equalizer, EqualizerHandle, EqualizerSettings};
+use crate::fade::{Fade, FadeHandle};
+use crate::queue::{PlayQueue, QueueState, RepeatMode};
+use crate::loudness::LoudnessInfo;
+use crate::replaygain::{Gain, GainHandle, ReplayGain, ReplayGainMode};
+use crate::session::{self, Session};
+use crate::settings::{self, AppSettings};
+use crate::silence::{HeldAudio, SilenceHandle, SilenceSettings, SkipSilence};
+use crate::speed::{LoopHandle, MediaClock, Speed, SpeedHandle};
+use crate::stereo::{Stereo, StereoHandle, StereoSettings};
+use crate::visualizer::{self, Tap, VisualizerHandle, VisualizerSettings};
+
+// How often the audio thread wakes up to notice finished tracks when no command arrives
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+// How often `playback-position` is emitted while a track is playing
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+// Crossfade used when the user skips tracks manually, capped by the configured length
+const SKIP_CROSSFADE: Duration = Duration::from_millis(750);
+pub const MAX_CROSSFADE_SECONDS: f32 = 12.0;
+pub const MAX_TRANSPORT_FADE_MS: u32 = 500;
+// How often a missing or non-preferred output device is looked for again
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+// How long a seek command waits for the audio thread to report where it landed
+const SEEK_TIMEOUT: Duration = Duration::from_secs(5);
+// Changes are saved to the session once things have been quiet for this long
+const SESSION_SAVE_DELAY: Duration = Duration::from_secs(1);
+// How often the position is saved to the session while playing
+const SESSION_POSITION_INTERVAL: Duration = Duration::from_secs(10);
+// How long exiting waits for the audio thread to save the session itself
+const SESSION_EXIT_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub enum AudioCommand {
+    // Sent ahead of the commands that queue these tracks
+    Describe(HashMap<String, TrackInfo>),
     Play(String),
     Toggle,
     Stop,
-    Seek(u64),
+    Seek { to: SeekTo, reply: Option<SeekReply> },
     SetVolume(f32),
+    SetQueue { paths: Vec<String>, start: Option<usize> },
+    Next,
+    Previous,
+    Jump(usize),
+    InsertNext(Vec<String>),
+    Append(Vec<String>),
+    MoveTrack { from: usize, to: usize },
+    RemoveTrack(usize),
+    ClearQueue,
+    SetShuffle(bool),
+    SetRepeat(RepeatMode),
+    ApplySettings(AppSettings),
+    SetEqualizer(EqualizerSettings),
+    SetOutputDevice(Option<String>),
+    SetSpeed { speed: f32, preserve_pitch: bool },
+    SetLoop(Option<(Duration, Duration)>),
+    SetSleepTimer(Option<SleepTimer>),
+    SetStereo(StereoSettings),
+    // The app is exiting: saves the session one last time and answers once written
+    SaveSession(mpsc::Sender<()>),
 }
 
 pub struct AudioPlayer {
     sender: Mutex<mpsc::Sender<AudioCommand>>,
+    state: Arc<Mutex<PlayerState>>,
+    queue: Arc<Mutex<QueueState>>,
+    visualizer: VisualizerHandle,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+/// Snapshot of the audio thread, refreshed on every tick for `get_player_state`.
+#[derive(Clone, Default, Serialize)]
+pub struct PlayerState {
+    pub path: Option<String>,
+    pub position_ms: u64,
+    pub duration_ms: Option<u64>,
+    pub paused: bool,
+    pub state: PlaybackState,
+    pub volume: f32,
+    pub last_error: Option<String>,
+    pub output_device: Option<String>, // None when no device could be opened
+    pub speed: f32,
+    pub preserve_pitch: bool,
+    pub loop_start_ms: Option<u64>,
+    pub loop_end_ms: Option<u64>,
+    pub sleep_timer: Option<SleepTimerStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SleepAction {
+    Stop,
+    Quit,
+}
+
+/// Where to seek to, relative offsets count from the current position or from the
+/// target of a seek still waiting on its fade.
+#[derive(Clone, Copy)]
+pub enum SeekTo {
+    Absolute(Duration),
+    Relative { millis: i64 },
+}
+
+// Receives the position a seek actually landed on
+type SeekReply = mpsc::Sender<Result<Duration, String>>;
+
+/// Transport actions that wait for the current track to fade out first.
+enum Transport {
+    Pause,
+    Stop,
+    Seek(Duration, Vec<SeekReply>),
+}
+
+#[derive(Clone, Copy)]
+enum SleepCountdown {
+    Until(Instant),
+    // Tracks left to finish, counting the current one
+    Tracks(u32),
+}
+
+#[derive(Clone, Copy)]
+pub struct SleepTimer {
+    countdown: SleepCountdown,
+    action: SleepAction,
+    fade: Duration,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SleepTimerStatus {
+    pub action: SleepAction,
+    // Unknown while more than one track is left
+    pub remaining_ms: Option<u64>,
+    pub tracks_left: Option<u32>,
+    pub fade_seconds: f32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct OutputDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct PositionPayload {
+    path: Option<String>,
+    position_ms: u64,
+    duration_ms: Option<u64>,
+    state: PlaybackState,
+}
+
+#[derive(Clone, Serialize)]
+struct TrackEndedPayload {
+    path: String,
+    next_path: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct SleepTimerPayload {
+    action: SleepAction,
+}
+
+#[derive(Clone, Serialize)]
+struct TrackErrorPayload {
+    path: String,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+struct UnplayablePayload {
+    path: String,
+    unplayable: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct OutputDevicePayload {
+    device: Option<String>,
+    error: Option<String>,
+}
+
+/// What the audio thread needs to know about a track besides its audio. It comes from
+/// the library before the track reaches the thread, so nothing is read from disk there.
+#[derive(Debug, Clone, Default)]
+pub struct TrackInfo {
+    album: Option<String>,
+    // From the tags, or the loudness analysis for files without any
+    replaygain: ReplayGain,
+    // Flagged in the library, which stays the authority on it
+    unplayable: bool,
+}
+
+/// A source that has been appended to the sink, front of the deque is the one playing.
+struct LoadedTrack {
+    path: String,
+    album: Option<String>,
+    duration: Option<Duration>,
+    // Where the source starts within the file, non-zero after a fallback seek
+    offset: Duration,
+    cancelled: Arc<AtomicBool>,
+    fade: FadeHandle,
+    replaygain: ReplayGain,
+    gain: GainHandle,
+    // File position of what is playing, the sink counts output time
+    clock: MediaClock,
+    // Read past `clock` but held back while deciding whether it is silence to skip
+    held: HeldAudio,
+    ab_loop: LoopHandle,
+    // Set once the crossfade into the next track has been decided on
+    crossfade_checked: bool,
+}
+
+/// Wraps a preloaded source so it can be dropped from the sink queue before it starts.
+struct Cancellable<S> {
+    inner: S,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<S: Source> Iterator for Cancellable<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.inner.next()
+    }
+}
+
+impl<S: Source> Source for Cancellable<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+pub(crate) fn open_source(path: &str) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("Error opening file: {}", e))?;
+    // Knowing the length lets the decoder seek with the file's own seek table or index
+    // instead of decoding its way there
+    let len = file.metadata().map_err(|e| format!("Error opening file: {}", e))?.len();
+    let mut builder = Decoder::builder()
+        .with_data(BufReader::new(file))
+        .with_byte_len(len)
+        .with_seekable(true);
+    if let Some(extension) = std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
+        builder = builder.with_hint(&extension.to_lowercase());
+    }
+    builder.build().map_err(|e| format!("Error decoding: {}", e))
+}
+
+/// Opens `path` positioned at `position`, returning the source and the track's full length.
+fn open_at(path: &str, position: Duration) -> Result<(Box<dyn Source + Send>, Option<Duration>), String> {
+    let mut source = open_source(path)?;
+    let duration = source.total_duration();
+    if position.is_zero() {
+        return Ok((Box::new(source), duration));
+    }
+
+    // The decoder's own seek jumps straight to the right packet, skipping has to decode
+    // everything before `position`
+    match source.try_seek(position) {
+        Ok(()) => Ok((Box::new(source), duration)),
+        Err(_) => {
+            // A failed seek may leave the decoder mid-stream, so start over
+            let source = open_source(path)?;
+            Ok((Box::new(source.skip_duration(position)), duration))
+        }
+    }
+}
+
+pub fn list_output_devices() -> Result<Vec<OutputDevice>, String> {
+    let host = cpal::default_host();
+    let default_name = host.default_output_device().and_then(|d| d.name().ok());
+    let devices = host.output_devices().map_err(|e| e.to_string())?;
+    Ok(devices
+        .filter_map(|d| d.name().ok())
+        .map(|name| OutputDevice {
+            is_default: default_name.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
+fn find_output_device(name: &str) -> Option<cpal::Device> {
+    cpal::default_host()
+        .output_devices()
+        .ok()?
+        .find(|d| d.name().is_ok_and(|n| n == name))
+}
+
+/// Looks up `paths` in the library, tracks outside it play without ReplayGain.
+fn load_track_info(paths: &[String]) -> HashMap<String, TrackInfo> {
+    let info = database::with_db(|conn| {
+        let mut info = HashMap::with_capacity(paths.len());
+        // Stays well below SQLite's limit on parameters per statement
+        for chunk in paths.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = conn.prepare(&format!(
+                "SELECT s.path, s.replaygain_track_gain, s.replaygain_track_peak, s.replaygain_album_gain,
+                        s.replaygain_album_peak, l.integrated_lufs, l.true_peak, l.album_lufs, l.album_peak,
+                        l.modified, al.title, s.unplayable
+                 FROM songs s
+                 LEFT JOIN albums al ON al.id = s.album_id
+                 LEFT JOIN loudness l ON l.path = s.path AND l.modified = s.modified_secs
+                 WHERE s.path IN ({})",
+                placeholders
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| {
+                let tagged = ReplayGain {
+                    track_gain: row.get(1)?,
+                    track_peak: row.get(2)?,
+                    album_gain: row.get(3)?,
+                    album_peak: row.get(4)?,
+                };
+                let measured = match row.get::<_, Option<f64>>(5)? {
+                    Some(integrated_lufs) => Some(LoudnessInfo {
+                        integrated_lufs,
+                        true_peak: row.get(6)?,
+                        album_lufs: row.get(7)?,
+                        album_peak: row.get(8)?,
+                        modified: row.get(9)?,
+                    }),
+                    None => None,
+                };
+                let replaygain = match measured {
+                    Some(measured) if tagged.track_gain.is_none() && tagged.album_gain.is_none() => measured.replaygain(),
+                    _ => tagged,
+                };
+                Ok((row.get::<_, String>(0)?, TrackInfo { album: row.get(10)?, replaygain, unplayable: row.get(11)? }))
+            })?;
+            for row in rows {
+                let (path, track) = row?;
+                info.insert(path, track);
+            }
+        }
+        Ok(info)
+    });
+    info.unwrap_or_else(|e| {
+        eprintln!("Failed to look up tracks: {}", e);
+        HashMap::new()
+    })
+}
+
+/// Consecutive tracks of the same album are often continuous, so they play gapless instead.
+fn is_same_album(a: &LoadedTrack, b: &LoadedTrack) -> bool {
+    match (&a.album, &b.album) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// State owned by the audio thread.
+struct AudioThread {
+    app: AppHandle,
+    shared: Arc<Mutex<PlayerState>>,
+    shared_queue: Arc<Mutex<QueueState>>,
+    // None while no output device could be opened
+    stream: Option<OutputStream>,
+    // Set from the stream's error callback, e.g. when the device is unplugged
+    stream_failed: Arc<AtomicBool>,
+    preferred_device: Option<String>,
+    device_name: Option<String>,
+    device_error: Option<String>,
+    last_device_check: Instant,
+    mixer: Mixer,
+    sink: Sink,
+    // Previous sinks still fading out, dropped once their deadline passes
+    fading_out: Vec<(Sink, Instant)>,
+    // Sources currently appended to the sink, in playback order
+    loaded: VecDeque<LoadedTrack>,
+    // What plays after the current track, the head of `loaded` is its current one
+    queue: PlayQueue,
+    // Move on to the next track when one fails to open, rather than stopping
+    skip_unplayable: bool,
+    // Library details of the queued tracks
+    track_info: HashMap<String, TrackInfo>,
+    current_volume: f32,
+    crossfade: Duration,
+    replaygain_mode: ReplayGainMode,
+    replaygain_preamp_db: f32,
+    // Shared by every appended track so EQ edits apply without rebuilding the sink
+    equalizer: EqualizerHandle,
+    speed: SpeedHandle,
+    preserve_pitch: bool,
+    silence: SilenceHandle,
+    stereo: StereoHandle,
+    visualizer: VisualizerHandle,
+    // A–B loop of the current track, carried over when its source is reopened
+    loop_region: Option<(Duration, Duration)>,
+    sleep_timer: Option<SleepTimer>,
+    // Ramp length for pause, resume, stop and seek, and the action waiting on it
+    transport_fade: Duration,
+    pending_transport: Option<(Transport, Instant)>,
+    last_state: PlaybackState,
+    last_position_emit: Instant,
+    last_error: Option<String>,
+    // When the session changed without being saved yet, and when it was last saved
+    session_changed: Option<Instant>,
+    last_session_save: Instant,
+    // The exit save has been written, which nothing later may overwrite
+    session_closed: bool,
+}
+
+impl AudioThread {
+    fn new(
+        app: AppHandle,
+        shared: Arc<Mutex<PlayerState>>,
+        shared_queue: Arc<Mutex<QueueState>>,
+        preferred_device: Option<String>,
+        visualizer: VisualizerHandle,
+    ) -> Self {
+        let (mixer, _) = mixer::mixer(2, 44100);
+        let sink = Sink::connect_new(&mixer);
+        let mut audio = Self {
+            app,
+            shared,
+            shared_queue,
+            stream: None,
+            stream_failed: Arc::new(AtomicBool::new(false)),
+            preferred_device,
+            device_name: None,
+            device_error: None,
+            last_device_check: Instant::now(),
+            mixer,
+            sink,
+            fading_out: Vec::new(),
+            loaded: VecDeque::new(),
+            queue: PlayQueue::new(),
+            skip_unplayable: true,
+            track_info: HashMap::new(),
+            current_volume: 0.5, // Default volume
+            crossfade: Duration::ZERO,
+            replaygain_mode: ReplayGainMode::Off,
+            replaygain_preamp_db: 0.0,
+            equalizer: EqualizerHandle::new(EqualizerSettings::default()),
+            speed: SpeedHandle::new(1.0, true),
+            preserve_pitch: true,
+            silence: SilenceHandle::new(&SilenceSettings::default()),
+            stereo: StereoHandle::new(&StereoSettings::default()),
+            visualizer,
+            loop_region: None,
+            sleep_timer: None,
+            transport_fade: Duration::ZERO,
+            pending_transport: None,
+            last_state: PlaybackState::Stopped,
+            last_position_emit: Instant::now(),
+            last_error: None,
+            session_changed: None,
+            last_session_save: Instant::now(),
+            session_closed: false,
+        };
+        audio.open_output();
+        audio.reset_sink(Duration::ZERO);
+        audio
+    }
+
+    fn current_path(&self) -> Option<&str> {
+        self.loaded.front().map(|t| t.path.as_str())
+    }
+
+    fn state(&self) -> PlaybackState {
+        if self.loaded.is_empty() {
+            PlaybackState::Stopped
+        } else if self.sink.is_paused() {
+            PlaybackState::Paused
+        } else {
+            PlaybackState::Playing
+        }
+    }
+
+    fn position(&self) -> Duration {
+        self.loaded.front().map_or(Duration::ZERO, |t| {
+            let held = t.held.get().mul_f32(self.speed.speed());
+            t.offset + t.clock.get().saturating_sub(held)
+        })
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.loaded.front().and_then(|t| t.duration)
+    }
+
+    /// Reports a track that failed to open or decode, and flags it in the library.
+    fn report_error(&mut self, path: &str, error: String) {
+        eprintln!("{}", error);
+        self.last_error = Some(format!("{}: {}", path, error));
+        let _ = self.app.emit("track-error", TrackErrorPayload {
+            path: path.to_string(),
+            error,
+        });
+        self.set_unplayable(path, true);
+    }
+
+    /// Updates the flag in the library, which reports whether it changed. Clearing it is
+    /// skipped for tracks not flagged when queued, to keep writes off every track change.
+    fn set_unplayable(&mut self, path: &str, unplayable: bool) {
+        if !unplayable && !self.track_info.get(path).is_some_and(|track| track.unplayable) {
+            return;
+        }
+        match mark_unplayable(path, unplayable) {
+            Ok(changed) => {
+                if let Some(track) = self.track_info.get_mut(path) {
+                    track.unplayable = unplayable;
+                }
+                if changed {
+                    let _ = self.app.emit("song-unplayable-changed", UnplayablePayload {
+                        path: path.to_string(),
+                        unplayable,
+                    });
+                }
+            },
+            Err(e) => eprintln!("Failed to update library cache: {}", e),
+        }
+    }
+
+    fn emit_position(&mut self) {
+        let _ = self.app.emit("playback-position", PositionPayload {
+            path: self.current_path().map(|p| p.to_string()),
+            position_ms: self.position().as_millis() as u64,
+            duration_ms: self.duration().map(|d| d.as_millis() as u64),
+            state: self.state(),
+        });
+        self.last_position_emit = Instant::now();
+    }
+
+    /// Copies the current state into the snapshot shared with `AudioPlayer`.
+    fn publish(&self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            let state = self.state();
+            *shared = PlayerState {
+                path: self.current_path().map(|p| p.to_string()),
+                position_ms: self.position().as_millis() as u64,
+                duration_ms: self.duration().map(|d| d.as_millis() as u64),
+                paused: state == PlaybackState::Paused,
+                state,
+                volume: self.current_volume,
+                last_error: self.last_error.clone(),
+                output_device: self.device_name.clone(),
+                speed: self.speed.speed(),
+                preserve_pitch: self.preserve_pitch,
+                loop_start_ms: self.loop_region.map(|(a, _)| a.as_millis() as u64),
+                loop_end_ms: self.loop_region.map(|(_, b)| b.as_millis() as u64),
+                sleep_timer: self.sleep_timer.map(|timer| SleepTimerStatus {
+                    action: timer.action,
+                    remaining_ms: self.sleep_remaining().map(|d| d.as_millis() as u64),
+                    tracks_left: match timer.countdown {
+                        SleepCountdown::Tracks(left) => Some(left),
+                        SleepCountdown::Until(_) => None,
+                    },
+                    fade_seconds: timer.fade.as_secs_f32(),
+                }),
+            };
+        }
+    }
+
+    fn handle(&mut self, command: AudioCommand) {
+        // Queue edits mark the session from `emit_queue`
+        if matches!(command, AudioCommand::Toggle | AudioCommand::Stop | AudioCommand::Seek { .. } | AudioCommand::SetVolume(_)) {
+            self.session_changed.get_or_insert_with(Instant::now);
+        }
+        match command {
+            AudioCommand::Describe(info) => self.track_info.extend(info),
+            AudioCommand::Play(path) => {
+                // Plays from the queue when the track is in it, otherwise right before
+                // whatever was coming up next
+                match self.queue.index_of(&path) {
+                    Some(index) => self.queue.jump(index),
+                    None => {
+                        self.queue.insert_next(vec![path]);
+                        self.queue.next();
+                    },
+                }
+                self.play_current();
+            },
+            AudioCommand::Toggle => {
+                self.finish_transport();
+                if self.loaded.is_empty() {
+                    // Stopped, or asleep at the end of a track: start the queue's current one
+                    self.start_current(Duration::ZERO);
+                    self.emit_queue();
+                } else if self.sink.is_paused() {
+                    if let Some(current) = self.loaded.front() {
+                        current.fade.ramp_to(1.0, self.transport_fade);
+                    }
+                    self.sink.play();
+                } else {
+                    self.fade_out_then(Transport::Pause);
+                }
+            },
+            AudioCommand::Stop => {
+                self.finish_transport();
+                self.fade_out_then(Transport::Stop);
+            },
+            AudioCommand::Seek { to, reply } => {
+                if self.loaded.is_empty() {
+                    if let Some(reply) = reply {
+                        let _ = reply.send(Err("Nothing is playing".to_string()));
+                    }
+                    return;
+                }
+                let pending = match &self.pending_transport {
+                    Some((Transport::Seek(target, _), _)) => Some(*target),
+                    _ => None,
+                };
+                match pending {
+                    // Seeks in quick succession, e.g. while scrubbing, share a single fade
+                    Some(pending) => {
+                        let target = self.seek_target(to, pending);
+                        if let Some((Transport::Seek(position, replies), _)) = &mut self.pending_transport {
+                            *position = target;
+                            replies.extend(reply);
+                        }
+                    },
+                    None => {
+                        self.finish_transport();
+                        let target = self.seek_target(to, self.position());
+                        self.fade_out_then(Transport::Seek(target, reply.into_iter().collect()));
+                    },
+                }
+            },
+            AudioCommand::SetVolume(vol) => {
+                // vol should be 0.0 to 1.0
+                self.current_volume = vol.clamp(0.0, 1.0);
+                self.sink.set_volume(self.current_volume);
+            },
+            AudioCommand::SetLoop(region) => {
+                if self.loaded.is_empty() {
+                    return;
+                }
+                self.loop_region = region;
+                if let Some(track) = self.loaded.front() {
+                    track.ab_loop.set(region);
+                }
+                // The loop only catches playback running into B, so start over from A
+                // when already past it
+                if let Some((a, b)) = region {
+                    if self.position() >= b {
+                        self.seek(a);
+                    }
+                }
+            },
+            AudioCommand::SetStereo(stereo) => self.stereo.set(&stereo),
+            AudioCommand::SaveSession(done) => {
+                self.save_session();
+                self.session_closed = true;
+                let _ = done.send(());
+            },
+            AudioCommand::SetSleepTimer(timer) => {
+                self.sleep_timer = timer;
+                self.sink.set_volume(self.current_volume);
+                if self.sleep_holds_queue() {
+                    self.cancel_preloaded();
+                } else {
+                    self.preload_next();
+                }
+            },
+            AudioCommand::SetQueue { paths, start } => {
+                let queued: HashSet<&String> = paths.iter().collect();
+                self.track_info.retain(|path, _| queued.contains(path));
+                self.queue.set_tracks(paths, start);
+                if self.queue.is_empty() {
+                    self.stop();
+                    self.emit_queue();
+                } else {
+                    self.play_current();
+                }
+            },
+            AudioCommand::Next => {
+                if self.queue.next().is_some() {
+                    self.play_current();
+                }
+            },
+            AudioCommand::Previous => {
+                if self.queue.previous().is_some() {
+                    self.play_current();
+                }
+            },
+            AudioCommand::Jump(index) => {
+                if index < self.queue.len() {
+                    self.queue.jump(index);
+                    self.play_current();
+                }
+            },
+            AudioCommand::InsertNext(paths) => {
+                self.queue.insert_next(paths);
+                self.queue_changed();
+            },
+            AudioCommand::Append(paths) => {
+                self.queue.append(paths);
+                self.queue_changed();
+            },
+            AudioCommand::MoveTrack { from, to } => {
+                self.queue.move_track(from, to);
+                self.queue_changed();
+            },
+            AudioCommand::RemoveTrack(index) => {
+                let playing = !self.loaded.is_empty();
+                if self.queue.remove(index) && playing {
+                    if self.queue.is_empty() {
+                        self.stop();
+                        self.emit_queue();
+                    } else {
+                        self.play_current();
+                    }
+                } else {
+                    self.queue_changed();
+                }
+            },
+            AudioCommand::ClearQueue => {
+                self.queue.clear();
+                self.queue_changed();
+            },
+            AudioCommand::SetShuffle(shuffle) => {
+                self.queue.set_shuffle(shuffle);
+                self.queue_changed();
+            },
+            AudioCommand::SetRepeat(repeat) => {
+                self.queue.set_repeat(repeat);
+                self.queue_changed();
+            },
+            AudioCommand::ApplySettings(settings) => self.apply_settings(&settings),
+            AudioCommand::SetEqualizer(equalizer) => self.equalizer.set(equalizer),
+            AudioCommand::SetSpeed { speed, preserve_pitch } => self.set_speed(speed, preserve_pitch),
+            AudioCommand::SetOutputDevice(name) => {
+                self.preferred_device = name;
+                self.reopen_output();
+            },
+        }
+    }
+
+    /// Plays the queue's current track from the start, crossfading briefly from
+    /// whatever was playing.
+    fn play_current(&mut self) {
+        // Manual skips get a short crossfade instead of a hard cut
+        self.pending_transport = None;
+        let fade = if self.state() == PlaybackState::Playing {
+            self.crossfade.min(SKIP_CROSSFADE).max(self.transport_fade)
+        } else {
+            Duration::ZERO
+        };
+
+        // FORCE RESET: Create a brand new Sink for every track.
+        // This ensures no leftover buffers, timing offsets, or "finished" states
+        // persist from the previous track.
+        self.reset_sink(fade);
+        self.loop_region = None;
+        if self.start_current(fade) {
+            self.last_error = self.device_error.clone();
+        }
+        self.emit_queue();
+    }
+
+    /// Starts the queue's current track on the sink, moving past tracks that fail to open
+    /// unless that is turned off.
+    fn start_current(&mut self, fade_in: Duration) -> bool {
+        for _ in 0..self.queue.len() {
+            let path = match self.queue.current() {
+                Some(path) => path,
+                None => return false,
+            };
+            if self.start(path, Duration::ZERO, fade_in) {
+                return true;
+            }
+            if !self.skip_unplayable || self.queue.skip().is_none() {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Called after the queue was edited: keeps the preloaded track in line with what
+    /// now comes next, and tells the frontend.
+    fn queue_changed(&mut self) {
+        let preloaded = self.loaded.iter()
+            .skip(1)
+            .find(|t| !t.cancelled.load(Ordering::Relaxed))
+            .map(|t| t.path.clone());
+        if preloaded.is_some() && preloaded != self.queue.peek_next() {
+            self.cancel_preloaded();
+        }
+        self.preload_next();
+        self.emit_queue();
+    }
+
+    fn emit_queue(&mut self) {
+        self.session_changed.get_or_insert_with(Instant::now);
+        let state = self.queue.state();
+        if let Ok(mut shared) = self.shared_queue.lock() {
+            *shared = state.clone();
+        }
+        let _ = self.app.emit("queue-changed", state);
+    }
+
+    /// Picks up the queue from the last session, paused at the position it was left at.
+    fn restore_session(&mut self, session: Session) {
+        self.current_volume = session.volume.clamp(0.0, 1.0);
+        self.sink.set_volume(self.current_volume);
+        self.queue.restore(session.tracks, session.order, session.current_index, session.shuffle, session.repeat);
+
+        if let Some(path) = self.queue.current() {
+            let position = Duration::from_millis(session.position_ms);
+            self.sink.pause();
+            match open_at(&path, position) {
+                Ok((source, duration)) => {
+                    self.append(path, source, duration, position, Duration::ZERO);
+                    self.preload_next();
+                },
+                Err(e) => self.report_error(&path, e),
+            }
+        }
+        self.emit_queue();
+        self.session_changed = None;
+    }
+
+    fn save_session(&mut self) {
+        if self.session_closed {
+            return;
+        }
+        let queue = self.queue.state();
+        let session = Session {
+            tracks: queue.tracks,
+            order: queue.order,
+            current_index: queue.current_index,
+            position_ms: self.position().as_millis() as u64,
+            volume: self.current_volume,
+            shuffle: queue.shuffle,
+            repeat: queue.repeat,
+        };
+        if let Err(e) = session::write_session(&session) {
+            eprintln!("Failed to save session: {}", e);
+        }
+        self.session_changed = None;
+        self.last_session_save = Instant::now();
+    }
+
+    fn seek(&mut self, position: Duration) {
+        // Try native seeking first (fast). Without an output nothing services the
+        // seek request and `try_seek` would block, so go straight to reopening.
+        let seek_result = match self.stream {
+            Some(_) => self.sink.try_seek(position).map_err(|e| e.to_string()),
+            None => Err("No output device".to_string()),
+        };
+
+        if seek_result.is_ok() {
+            // Native seeks are absolute, so the reported position needs no offset
+            if let Some(track) = self.loaded.front_mut() {
+                track.offset = Duration::ZERO;
+                track.fade.ramp_to(1.0, self.transport_fade);
+            }
+        } else {
+            // Fallback: re-open file at the new position
+            if let Some(path) = self.current_path().map(|p| p.to_string()) {
+                // For fallback seek, we also want a fresh start to avoid glitches
+                self.reset_sink(Duration::ZERO);
+                self.start(path, position, self.transport_fade);
+            }
+        }
+    }
+
+    /// Runs `action` once the current track has faded out, or straight away when
+    /// nothing is audible.
+    fn fade_out_then(&mut self, action: Transport) {
+        let audible = self.state() == PlaybackState::Playing;
+        match self.loaded.front() {
+            Some(current) if audible && !self.transport_fade.is_zero() => {
+                current.fade.ramp_to(0.0, self.transport_fade);
+                self.pending_transport = Some((action, Instant::now() + self.transport_fade));
+            },
+            _ => self.run_transport(action),
+        }
+    }
+
+    fn run_transport(&mut self, action: Transport) {
+        match action {
+            Transport::Pause => {
+                self.sink.pause();
+                self.fading_out.clear();
+            },
+            Transport::Stop => self.stop(),
+            Transport::Seek(position, replies) => {
+                self.seek(position);
+                let landed = if self.loaded.is_empty() {
+                    Err("Failed to seek".to_string())
+                } else {
+                    Ok(self.position())
+                };
+                for reply in replies {
+                    let _ = reply.send(landed.clone());
+                }
+            },
+        }
+    }
+
+    /// Resolves `to` against `from`, kept within the current track.
+    fn seek_target(&self, to: SeekTo, from: Duration) -> Duration {
+        let target = match to {
+            SeekTo::Absolute(position) => position,
+            SeekTo::Relative { millis } if millis < 0 => from.saturating_sub(Duration::from_millis(millis.unsigned_abs())),
+            SeekTo::Relative { millis } => from + Duration::from_millis(millis as u64),
+        };
+        match self.duration() {
+            Some(duration) => target.min(duration),
+            None => target,
+        }
+    }
+
+    /// Completes a transport action still waiting on its fade.
+    fn finish_transport(&mut self) {
+        if let Some((action, _)) = self.pending_transport.take() {
+            self.run_transport(action);
+        }
+    }
+
+    /// How long the thread may wait for a command before polling again.
+    fn next_wakeup(&self) -> Duration {
+        match &self.pending_transport {
+            Some((_, at)) => at.saturating_duration_since(Instant::now()).min(TICK_INTERVAL),
+            None => TICK_INTERVAL,
+        }
+    }
+
+    fn set_speed(&mut self, speed: f32, preserve_pitch: bool) {
+        // Applies to the playing and preloaded tracks straight away
+        self.speed.set(speed, preserve_pitch);
+        self.preserve_pitch = preserve_pitch;
+    }
+
+    /// Opens the preferred output device, falling back to the system default. When no
+    /// device can be opened, sinks play into a mixer nothing reads from so commands keep
+    /// working, and the device check retries later.
+    fn open_output(&mut self) {
+        self.stream = None;
+        self.stream_failed.store(false, Ordering::Relaxed);
+        self.last_device_check = Instant::now();
+
+        let failed = self.stream_failed.clone();
+        let on_error = move |e: cpal::StreamError| {
+            eprintln!("Audio stream error: {}", e);
+            failed.store(true, Ordering::Relaxed);
+        };
+
+        let candidates = self
+            .preferred_device
+            .as_deref()
+            .and_then(find_output_device)
+            .into_iter()
+            .chain(cpal::default_host().default_output_device());
+
+        let mut error = "No audio output device found".to_string();
+        for device in candidates {
+            let name = device.name().ok();
+            let opened = OutputStreamBuilder::from_device(device)
+                .map(|builder| builder.with_error_callback(on_error.clone()))
+                .and_then(|builder| builder.open_stream_or_fallback());
+            match opened {
+                Ok(mut stream) => {
+                    stream.log_on_drop(false);
+                    // Sinks play into a mixer of our own so the stereo stage and visualizer
+                    // get the whole mix, crossfades included
+                    let config = stream.config();
+                    let (mixer, source) = mixer::mixer(config.channel_count(), config.sample_rate());
+                    stream.mixer().add(Tap::new(Stereo::new(source, &self.stereo), &self.visualizer));
+                    self.mixer = mixer;
+                    self.stream = Some(stream);
+                    self.set_output_device(name, None);
+                    return;
+                },
+                Err(e) => error = format!("Failed to open audio output: {}", e),
+            }
+        }
+
+        eprintln!("{}", error);
+        self.mixer = mixer::mixer(2, 44100).0;
+        self.last_error = Some(error.clone());
+        self.set_output_device(None, Some(error));
+    }
+
+    fn set_output_device(&mut self, device: Option<String>, error: Option<String>) {
+        if device == self.device_name && error == self.device_error {
+            return;
+        }
+        self.device_name = device.clone();
+        self.device_error = error.clone();
+        let _ = self.app.emit("output-device-changed", OutputDevicePayload { device, error });
+    }
+
+    /// Moves playback to a freshly opened output, resuming the current track where it was.
+    fn reopen_output(&mut self) {
+        let resume = self
+            .current_path()
+            .map(|p| (p.to_string(), self.position(), self.sink.is_paused()));
+
+        self.fading_out.clear();
+        self.open_output();
+        self.reset_sink(Duration::ZERO);
+
+        if let Some((path, position, paused)) = resume {
+            if self.start(path, position, Duration::ZERO) && paused {
+                self.sink.pause();
+            }
+        }
+    }
+
+    /// Reopens the output when the stream failed, no device was available, or the
+    /// preferred device has come back.
+    fn check_output(&mut self) {
+        if self.stream_failed.load(Ordering::Relaxed) {
+            self.reopen_output();
+            return;
+        }
+        if self.last_device_check.elapsed() < DEVICE_CHECK_INTERVAL {
+            return;
+        }
+        self.last_device_check = Instant::now();
+
+        let preferred_back = match &self.preferred_device {
+            Some(name) => self.device_name.as_ref() != Some(name) && find_output_device(name).is_some(),
+            None => false,
+        };
+        if self.stream.is_none() || preferred_back {
+            self.reopen_output();
+        }
+    }
+
+    fn apply_settings(&mut self, settings: &AppSettings) {
+        self.crossfade = Duration::from_secs_f32(settings.crossfade_seconds.clamp(0.0, MAX_CROSSFADE_SECONDS));
+
+        // Gain changes apply to the playing track right away, not just the next one
+        self.replaygain_mode = settings.replaygain_mode;
+        self.replaygain_preamp_db = settings.replaygain_preamp_db;
+        for track in &self.loaded {
+            track.gain.set(track.replaygain.factor(self.replaygain_mode, self.replaygain_preamp_db));
+        }
+        self.equalizer.set(settings.equalizer.clone());
+        self.set_speed(settings.playback_speed, settings.preserve_pitch);
+        self.silence.set(&settings.skip_silence);
+        self.stereo.set(&settings.stereo);
+        self.transport_fade = Duration::from_millis(settings.transport_fade_ms.min(MAX_TRANSPORT_FADE_MS) as u64);
+        self.skip_unplayable = settings.skip_unplayable;
+
+        if settings.output_device != self.preferred_device {
+            self.preferred_device = settings.output_device.clone();
+            self.reopen_output();
+        }
+    }
+
+    fn emit_track_ended(&self, path: &str) {
+        let _ = self.app.emit("track-ended", TrackEndedPayload {
+            path: path.to_string(),
+            next_path: self.current_path().map(|p| p.to_string()),
+        });
+    }
+
+    /// Called on every tick to drop tracks the sink has finished, keep the next one
+    /// decoded and report the playback clock to the frontend.
+    fn poll(&mut self) {
+        self.check_output();
+        if self.pending_transport.as_ref().is_some_and(|(_, at)| Instant::now() >= *at) {
+            self.finish_transport();
+        }
+
+        let now = Instant::now();
+        self.fading_out.retain(|(_, until)| *until > now);
+
+        let finished = self.loaded.len().saturating_sub(self.sink.len());
+        if finished > 0 {
+            let ended: Vec<LoadedTrack> = self.loaded
+                .drain(..finished)
+                .filter(|t| !t.cancelled.load(Ordering::Relaxed))
+                .collect();
+            if !ended.is_empty() {
+                let mut has_next = false;
+                for _ in &ended {
+                    self.finish_track();
+                    has_next = self.queue.advance().is_some();
+                }
+                // The sink ran dry without a preloaded track, e.g. because it failed to open
+                let asleep = matches!(self.sleep_timer, Some(SleepTimer { countdown: SleepCountdown::Tracks(0), .. }));
+                if self.loaded.is_empty() && has_next && !asleep {
+                    self.start_current(Duration::ZERO);
+                }
+                for track in &ended {
+                    self.emit_track_ended(&track.path);
+                }
+                self.emit_queue();
+            }
+            self.preload_next();
+        }
+        self.maybe_crossfade();
+        self.check_sleep_timer();
+
+        if self.state() == PlaybackState::Playing && self.last_session_save.elapsed() >= SESSION_POSITION_INTERVAL {
+            self.session_changed.get_or_insert_with(Instant::now);
+        }
+        if self.session_changed.is_some_and(|at| at.elapsed() >= SESSION_SAVE_DELAY) {
+            self.save_session();
+        }
+
+        let state = self.state();
+        if state != self.last_state
+            || (state == PlaybackState::Playing && self.last_position_emit.elapsed() >= POSITION_INTERVAL)
+        {
+            self.last_state = state;
+            self.emit_position();
+        }
+        self.publish();
+    }
+
+    fn stop(&mut self) {
+        self.sink.stop();
+        self.fading_out.clear();
+        self.unload();
+        self.loop_region = None;
+    }
+
+    /// Bookkeeping for the current track having played to its end.
+    fn finish_track(&mut self) {
+        self.loop_region = None;
+        if let Some(timer) = self.sleep_timer.as_mut() {
+            if let SleepCountdown::Tracks(left) = &mut timer.countdown {
+                *left = left.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Whether the sleep timer fires at the end of the current track.
+    fn sleep_holds_queue(&self) -> bool {
+        matches!(self.sleep_timer, Some(SleepTimer { countdown: SleepCountdown::Tracks(left), .. }) if left <= 1)
+    }
+
+    /// Time until the sleep timer fires, when it is known.
+    fn sleep_remaining(&self) -> Option<Duration> {
+        match self.sleep_timer?.countdown {
+            SleepCountdown::Until(at) => Some(at.saturating_duration_since(Instant::now())),
+            SleepCountdown::Tracks(1) => {
+                let left = self.duration()?.saturating_sub(self.position());
+                Some(left.div_f32(self.speed.speed()))
+            },
+            SleepCountdown::Tracks(_) => None,
+        }
+    }
+
+    /// Fades the volume out over the timer's last seconds and pauses once it runs out.
+    fn check_sleep_timer(&mut self) {
+        let timer = match self.sleep_timer {
+            Some(timer) => timer,
+            None => return,
+        };
+        let fired = match timer.countdown {
+            SleepCountdown::Until(at) => Instant::now() >= at,
+            SleepCountdown::Tracks(left) => left == 0,
+        };
+        if !fired {
+            let factor = match self.sleep_remaining() {
+                Some(remaining) if remaining < timer.fade => remaining.as_secs_f32() / timer.fade.as_secs_f32(),
+                _ => 1.0,
+            };
+            self.sink.set_volume(self.current_volume * factor);
+            return;
+        }
+
+        // Paused rather than stopped, so playback can pick up where it fell asleep
+        self.sleep_timer = None;
+        self.sink.pause();
+        self.fading_out.clear();
+        self.sink.set_volume(self.current_volume);
+        let _ = self.app.emit("sleep-timer-fired", SleepTimerPayload { action: timer.action });
+        if timer.action == SleepAction::Quit {
+            self.app.exit(0);
+        }
+    }
+
+    /// Starts the next track on a fresh sink while the current one fades out, once
+    /// the current track is within the crossfade length of its end.
+    fn maybe_crossfade(&mut self) {
+        if self.crossfade.is_zero() || self.state() != PlaybackState::Playing {
+            return;
+        }
+        let position = self.position();
+        let (current, next) = match (self.loaded.front(), self.loaded.get(1)) {
+            (Some(current), Some(next)) if !current.crossfade_checked && !next.cancelled.load(Ordering::Relaxed) => {
+                (current, next)
+            },
+            _ => return,
+        };
+        // Fades run in real time, which the playback speed stretches or shrinks
+        let remaining = match current.duration {
+            Some(duration) => duration.saturating_sub(position).div_f32(self.speed.speed()),
+            None => return,
+        };
+        if remaining > self.crossfade {
+            return;
+        }
+
+        let current_path = current.path.clone();
+        let same_album = is_same_album(current, next);
+        if let Some(current) = self.loaded.front_mut() {
+            current.crossfade_checked = true;
+        }
+        if same_album || remaining.is_zero() {
+            return;
+        }
+
+        // The preloaded track is dropped and reopened on the new sink
+        self.reset_sink(remaining);
+        if self.queue.advance().is_some() && self.start_current(remaining) {
+            self.finish_track();
+            self.emit_track_ended(&current_path);
+        }
+        self.emit_queue();
+    }
+
+    /// Opens `path` at `position` on the current sink and starts playing it.
+    fn start(&mut self, path: String, position: Duration, fade_in: Duration) -> bool {
+        match open_at(&path, position) {
+            Ok((source, duration)) => {
+                self.append(path, source, duration, position, fade_in);
+                self.sink.play();
+                self.preload_next();
+                true
+            },
+            Err(e) => {
+                self.report_error(&path, e);
+                false
+            },
+        }
+    }
+
+    /// `offset` is where `source` starts within the file, the sink only counts from there.
+    fn append<S>(&mut self, path: String, source: S, duration: Option<Duration>, offset: Duration, fade_in: Duration)
+    where
+        S: Source + Send + 'static,
+    {
+        // It opened, so it isn't unplayable (any more)
+        self.set_unplayable(&path, false);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let fade = if fade_in.is_zero() {
+            FadeHandle::new(1.0)
+        } else {
+            let fade = FadeHandle::new(0.0);
+            fade.ramp_to(1.0, fade_in);
+            fade
+        };
+        let info = self.track_info.get(&path).cloned().unwrap_or_default();
+        let gain = GainHandle::new(info.replaygain.factor(self.replaygain_mode, self.replaygain_preamp_db));
+        let clock = MediaClock::default();
+        // Only the current track loops, tracks queued behind it start without one
+        let ab_loop = LoopHandle::default();
+        if self.loaded.is_empty() {
+            ab_loop.set(self.loop_region);
+        }
+        let held = HeldAudio::default();
+        let source = SkipSilence::new(Speed::new(source, &self.speed, &ab_loop, &clock), &self.silence, &held, &self.speed);
+        self.sink.append(Cancellable {
+            inner: Fade::new(Equalizer::new(Gain::new(source, &gain), &self.equalizer), &fade),
+            cancelled: cancelled.clone(),
+        });
+        self.loaded.push_back(LoadedTrack {
+            path,
+            album: info.album,
+            duration,
+            offset,
+            cancelled,
+            fade,
+            replaygain: info.replaygain,
+            gain,
+            clock,
+            held,
+            ab_loop,
+            crossfade_checked: false,
+        });
+    }
+
+    /// Decodes the next track in the queue and appends it behind the current one so
+    /// the sink moves on to it without any silence in between.
+    fn preload_next(&mut self) {
+        // Nothing is playing, the queue waits for the next Play. When the sleep timer
+        // stops after this track, so does the sink.
+        if self.loaded.is_empty() || self.sleep_holds_queue() {
+            return;
+        }
+        let has_next = self.loaded.iter().skip(1).any(|t| !t.cancelled.load(Ordering::Relaxed));
+        if has_next {
+            return;
+        }
+
+        // A track that fails to open is reported and skipped once playback reaches it
+        if let Some(path) = self.queue.peek_next() {
+            if let Ok((source, duration)) = open_at(&path, Duration::ZERO) {
+                self.append(path, source, duration, Duration::ZERO, Duration::ZERO);
+            }
+        }
+    }
+
+    /// Drops every appended track after the current one.
+    fn cancel_preloaded(&mut self) {
+        for track in self.loaded.iter().skip(1) {
+            track.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Forgets every appended track, the queue still knows what comes next.
+    fn unload(&mut self) {
+        self.cancel_preloaded();
+        self.loaded.clear();
+    }
+
+    /// Replaces the sink with a fresh one. With a non-zero `fade_out` the old sink keeps
+    /// playing its current track while it fades, so the next one can fade in over it.
+    fn reset_sink(&mut self, fade_out: Duration) {
+        if let Some(current) = self.loaded.front() {
+            current.fade.ramp_to(0.0, fade_out);
+        }
+        self.unload();
+
+        let old = mem::replace(&mut self.sink, Sink::connect_new(&self.mixer));
+        self.sink.set_volume(self.current_volume);
+        if !fade_out.is_zero() {
+            self.fading_out.push((old, Instant::now() + fade_out));
+        }
+    }
 }
 
 impl AudioPlayer {
-    pub fn new() -> Self {
+    pub fn new(app: AppHandle) -> Self {
         let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(PlayerState {
+            volume: 0.5,
+            ..Default::default()
+        }));
+        let shared = state.clone();
+        let queue = Arc::new(Mutex::new(QueueState::default()));
+        let shared_queue = queue.clone();
+        let settings = settings::load_settings().unwrap_or_default();
+        let session = session::load_session();
+        let track_info = session.as_ref().map(|s| load_track_info(&s.tracks)).unwrap_or_default();
+        let visualizer = VisualizerHandle::new(settings.visualizer.clone());
+        visualizer::spawn(app.clone(), visualizer.clone());
+        let tap = visualizer.clone();
 
         // Spawn audio thread
         thread::spawn(move || {
-            // Create output stream using rodio 0.21 API
-            let stream = OutputStreamBuilder::open_default_stream()
-                .expect("Failed to open default audio stream");
-            let mixer = stream.mixer();
-            
-            // Create a Sink connected to the mixer
-            let mut sink = Sink::connect_new(&mixer);
-            let mut current_path: Option<String> = None;
-            let mut current_volume: f32 = 0.5; // Default volume
+            let mut audio = AudioThread::new(app, shared, shared_queue, settings.output_device.clone(), tap);
+            audio.apply_settings(&settings);
+            audio.track_info = track_info;
+            if let Some(session) = session {
+                audio.restore_session(session);
+            }
 
             loop {
-                if let Ok(command) = rx.recv() {
-                    match command {
-                        AudioCommand::Play(path) => {
-                            current_path = Some(path.clone());
-                            
-                            // FORCE RESET: Create a brand new Sink for every track.
-                            // This ensures no leftover buffers, timing offsets, or "finished" states
-                            // persist from the previous track.
-                            sink = Sink::connect_new(&mixer);
-                            sink.set_volume(current_volume);
-
-                            match File::open(&path) {
-                                Ok(file) => {
-                                    let reader = BufReader::new(file);
-                                    match Decoder::new(reader) {
-                                        Ok(source) => {
-                                            sink.append(source);
-                                            sink.play();
-                                        },
-                                        Err(e) => eprintln!("Error decoding: {}", e),
-                                    }
-                                },
-                                Err(e) => eprintln!("Error opening file: {}", e),
-                            }
-                        },
-                        AudioCommand::Toggle => {
-                            if sink.is_paused() {
-                                sink.play();
-                            } else {
-                                sink.pause();
-                            }
-                        },
-                        AudioCommand::Stop => {
-                            sink.stop();
-                        },
-                        AudioCommand::Seek(seconds) => {
-                            // Try native seeking first (fast)
-                            let seek_result = sink.try_seek(Duration::from_secs(seconds));
-                            
-                            if seek_result.is_err() {
-                                // Fallback: re-open file and skip
-                                if let Some(ref path) = current_path {
-                                    // For fallback seek, we also want a fresh start to avoid glitches
-                                    sink = Sink::connect_new(&mixer);
-                                    sink.set_volume(current_volume);
-                                    
-                                    match File::open(path) {
-                                        Ok(file) => {
-                                            let reader = BufReader::new(file);
-                                            match Decoder::new(reader) {
-                                                Ok(source) => {
-                                                    sink.append(source.skip_duration(Duration::from_secs(seconds)));
-                                                    sink.play();
-                                                },
-                                                Err(e) => eprintln!("Error decoding for seek: {}", e),
-                                            }
-                                        },
-                                        Err(e) => eprintln!("Error opening file for seek: {}", e),
-                                    }
-                                }
-                            }
-                        },
-                        AudioCommand::SetVolume(vol) => {
-                            // vol should be 0.0 to 1.0
-                            current_volume = vol.clamp(0.0, 1.0);
-                            sink.set_volume(current_volume);
-                        },
-                    }
+                match rx.recv_timeout(audio.next_wakeup()) {
+                    Ok(command) => audio.handle(command),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {},
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
                 }
+                audio.poll();
             }
         });
 
         Self {
             sender: Mutex::new(tx),
+            state,
+            queue,
+            visualizer,
         }
     }
 
-    pub fn play(&self, path: String) -> Result<(), String> {
+    fn send(&self, command: AudioCommand) -> Result<(), String> {
         self.sender
             .lock()
             .map_err(|_| "Failed to lock sender".to_string())?
-            .send(AudioCommand::Play(path))
+            .send(command)
             .map_err(|e| e.to_string())
     }
 
+    /// Sends what the library knows about `paths`, ahead of a command queueing them.
+    fn describe(&self, paths: &[String]) -> Result<(), String> {
+        self.send(AudioCommand::Describe(load_track_info(paths)))
+    }
+
+    pub fn play(&self, path: String) -> Result<(), String> {
+        self.describe(std::slice::from_ref(&path))?;
+        self.send(AudioCommand::Play(path))
+    }
+
     pub fn pause_toggle(&self) -> Result<(), String> {
-        self.sender
-            .lock()
-            .map_err(|_| "Failed to lock sender".to_string())?
-            .send(AudioCommand::Toggle)
-            .map_err(|e| e.to_string())
+        self.send(AudioCommand::Toggle)
     }
 
     pub fn stop(&self) -> Result<(), String> {
-        self.sender
-            .lock()
-            .map_err(|_| "Failed to lock sender".to_string())?
-            .send(AudioCommand::Stop)
-            .map_err(|e| e.to_string())
+        self.send(AudioCommand::Stop)
     }
 
-    pub fn seek(&self, seconds: u64) -> Result<(), String> {
-        self.sender
-            .lock()
-            .map_err(|_| "Failed to lock sender".to_string())?
-            .send(AudioCommand::Seek(seconds))
-            .map_err(|e| e.to_string())
+    /// Seeks and waits for it to happen, returning the position it landed on.
+    pub async fn seek(&self, to: SeekTo) -> Result<Duration, String> {
+        let (reply, landed) = mpsc::channel();
+        self.send(AudioCommand::Seek { to, reply: Some(reply) })?;
+        // The wait blocks, so it gets a thread of its own rather than an async runtime worker
+        let waited = tauri::async_runtime::spawn_blocking(move || landed.recv_timeout(SEEK_TIMEOUT))
+            .await
+            .map_err(|e| e.to_string())?;
+        match waited {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err("Seek timed out".to_string()),
+            // Playback moved on to another track before the seek ran
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err("Seek was interrupted".to_string()),
+        }
+    }
+
+    /// Pauses (or quits) after `duration`, fading out over the last `fade`.
+    pub fn sleep_after(&self, duration: Duration, action: SleepAction, fade: Duration) -> Result<(), String> {
+        self.send(AudioCommand::SetSleepTimer(Some(SleepTimer {
+            countdown: SleepCountdown::Until(Instant::now() + duration),
+            action,
+            fade,
+        })))
+    }
+
+    /// Pauses (or quits) once `tracks` more tracks, the current one included, have ended.
+    pub fn sleep_after_tracks(&self, tracks: u32, action: SleepAction, fade: Duration) -> Result<(), String> {
+        if tracks == 0 {
+            return Err("Track count must be at least 1".to_string());
+        }
+        self.send(AudioCommand::SetSleepTimer(Some(SleepTimer {
+            countdown: SleepCountdown::Tracks(tracks),
+            action,
+            fade,
+        })))
+    }
+
+    pub fn cancel_sleep_timer(&self) -> Result<(), String> {
+        self.send(AudioCommand::SetSleepTimer(None))
+    }
+
+    pub fn set_loop(&self, region: Option<(Duration, Duration)>) -> Result<(), String> {
+        if let Some((start, end)) = region {
+            if start >= end {
+                return Err("Loop start must be before its end".to_string());
+            }
+        }
+        self.send(AudioCommand::SetLoop(region))
     }
 
     pub fn set_volume(&self, volume: f32) -> Result<(), String> {
-        self.sender
+        self.send(AudioCommand::SetVolume(volume))
+    }
+
+    /// Replaces the queue and starts playing it from `start`.
+    pub fn set_queue(&self, paths: Vec<String>, start: Option<usize>) -> Result<(), String> {
+        self.describe(&paths)?;
+        self.send(AudioCommand::SetQueue { paths, start })
+    }
+
+    pub fn next(&self) -> Result<(), String> {
+        self.send(AudioCommand::Next)
+    }
+
+    pub fn previous(&self) -> Result<(), String> {
+        self.send(AudioCommand::Previous)
+    }
+
+    pub fn jump(&self, index: usize) -> Result<(), String> {
+        self.send(AudioCommand::Jump(index))
+    }
+
+    pub fn insert_next(&self, paths: Vec<String>) -> Result<(), String> {
+        self.describe(&paths)?;
+        self.send(AudioCommand::InsertNext(paths))
+    }
+
+    pub fn append(&self, paths: Vec<String>) -> Result<(), String> {
+        self.describe(&paths)?;
+        self.send(AudioCommand::Append(paths))
+    }
+
+    pub fn move_track(&self, from: usize, to: usize) -> Result<(), String> {
+        self.send(AudioCommand::MoveTrack { from, to })
+    }
+
+    pub fn remove_track(&self, index: usize) -> Result<(), String> {
+        self.send(AudioCommand::RemoveTrack(index))
+    }
+
+    /// Removes everything from the queue but the current track.
+    pub fn clear_queue(&self) -> Result<(), String> {
+        self.send(AudioCommand::ClearQueue)
+    }
+
+    pub fn set_shuffle(&self, shuffle: bool) -> Result<(), String> {
+        self.send(AudioCommand::SetShuffle(shuffle))
+    }
+
+    pub fn set_repeat(&self, repeat: RepeatMode) -> Result<(), String> {
+        self.send(AudioCommand::SetRepeat(repeat))
+    }
+
+    /// Saves the session for when the app exits. The audio thread writes it and then
+    /// stops saving, so a pending save can't land on top. Only when that thread doesn't
+    /// answer is it written straight from the shared snapshots.
+    pub fn save_session(&self) -> Result<(), String> {
+        let (done, saved) = mpsc::channel();
+        if self.send(AudioCommand::SaveSession(done)).is_ok() && saved.recv_timeout(SESSION_EXIT_TIMEOUT).is_ok() {
+            return Ok(());
+        }
+        let state = self.state()?;
+        let queue = self.queue()?;
+        session::write_session(&Session {
+            tracks: queue.tracks,
+            order: queue.order,
+            current_index: queue.current_index,
+            position_ms: state.position_ms,
+            volume: state.volume,
+            shuffle: queue.shuffle,
+            repeat: queue.repeat,
+        })
+    }
+
+    pub fn queue(&self) -> Result<QueueState, String> {
+        self.queue
             .lock()
-            .map_err(|_| "Failed to lock sender".to_string())?
-            .send(AudioCommand::SetVolume(volume))
-            .map_err(|e| e.to_string())
+            .map(|q| q.clone())
+            .map_err(|_| "Failed to lock queue".to_string())
     }
-}
 
+    pub fn apply_settings(&self, settings: AppSettings) -> Result<(), String> {
+        self.visualizer.configure(settings.visualizer.clone());
+        self.send(AudioCommand::ApplySettings(settings))
+    }
+
+    pub fn set_stereo(&self, stereo: StereoSettings) -> Result<(), String> {
+        self.send(AudioCommand::SetStereo(stereo))
+    }
+
+    pub fn set_visualizer(&self, settings: VisualizerSettings) {
+        self.visualizer.configure(settings);
+    }
+
+    pub fn set_equalizer(&self, equalizer: EqualizerSettings) -> Result<(), String> {
+        self.send(AudioCommand::SetEqualizer(equalizer))
+    }
+
+    pub fn set_speed(&self, speed: f32, preserve_pitch: bool) -> Result<(), String> {
+        self.send(AudioCommand::SetSpeed { speed, preserve_pitch })
+    }
+
+    pub fn set_output_device(&self, name: Option<String>) -> Result<(), String> {
+        self.send(AudioCommand::SetOutputDevice(name))
+    }
+
+    pub fn state(&self) -> Result<PlayerState, String> {
+        self.state
+            .lock()
+            .map(|s| s.clone())
+            .map_err(|_| "Failed to lock player state".to_string())
+    }
+}
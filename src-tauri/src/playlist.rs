@@ -1,7 +1,6 @@
 use serde::{Serialize, Deserialize};
-use std::fs;
-use std::path::PathBuf;
-use std::io::Write;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::database::{self, insert_playlist};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Playlist {
@@ -10,25 +9,24 @@ pub struct Playlist {
     pub tracks: Vec<String>, // List of file paths
 }
 
-fn get_playlist_dir() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap_or(PathBuf::from("."));
-    path.push("luma");
-    path.push("playlists");
-    let _ = fs::create_dir_all(&path);
-    path
+fn playlist_id(conn: &Connection, name: &str) -> rusqlite::Result<Option<i64>> {
+    conn.query_row("SELECT id FROM playlists WHERE name = ?1", [name], |row| row.get(0))
+        .optional()
+}
+
+fn load_playlist(conn: &Connection, id: i64) -> rusqlite::Result<Playlist> {
+    let (name, created_at) = conn.query_row(
+        "SELECT name, created_at FROM playlists WHERE id = ?1",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let mut stmt = conn.prepare("SELECT path FROM playlist_tracks WHERE playlist_id = ?1 ORDER BY position")?;
+    let tracks = stmt.query_map([id], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(Playlist { name, created_at, tracks })
 }
 
 #[tauri::command]
 pub fn create_playlist(name: String) -> Result<Playlist, String> {
-    let mut path = get_playlist_dir();
-    // Sanitize filename roughly
-    let safe_name = name.replace(|c: char| !c.is_alphanumeric() && c != ' ' && c != '-', ""); 
-    path.push(format!("{}.json", safe_name));
-
-    if path.exists() {
-        return Err("Playlist already exists".into());
-    }
-
     let playlist = Playlist {
         name,
         created_at: std::time::SystemTime::now()
@@ -38,101 +36,79 @@ pub fn create_playlist(name: String) -> Result<Playlist, String> {
         tracks: Vec::new(),
     };
 
-    let json = serde_json::to_string_pretty(&playlist).map_err(|e| e.to_string())?;
-    
-    let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-    file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
+    let created = database::with_db(|conn| {
+        if playlist_id(conn, &playlist.name)?.is_some() {
+            return Ok(false);
+        }
+        let tx = conn.transaction()?;
+        insert_playlist(&tx, &playlist)?;
+        tx.commit()?;
+        Ok(true)
+    })?;
+    if !created {
+        return Err("Playlist already exists".into());
+    }
 
     Ok(playlist)
 }
 
 #[tauri::command]
 pub fn get_playlists() -> Result<Vec<Playlist>, String> {
-    let dir = get_playlist_dir();
-    let mut playlists = Vec::new();
-
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().and_then(|e| e.to_str()) == Some("json") {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    if let Ok(pl) = serde_json::from_str::<Playlist>(&content) {
-                        playlists.push(pl);
-                    }
-                }
-            }
-        }
-    }
-    
-    // Sort by creation time newest first?
-    playlists.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    Ok(playlists)
+    database::with_db(|conn| {
+        // Newest first
+        let mut stmt = conn.prepare("SELECT id FROM playlists ORDER BY created_at DESC")?;
+        let ids = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<i64>>>()?;
+        ids.into_iter().map(|id| load_playlist(conn, id)).collect()
+    })
 }
 
 #[tauri::command]
 pub fn add_to_playlist(playlist_name: String, song_path: String) -> Result<Playlist, String> {
-    let dir = get_playlist_dir();
-    // We need to find the file that matches this name.
-    // Ideally we store filename as ID, but for now scan:
-    let safe_name = playlist_name.replace(|c: char| !c.is_alphanumeric() && c != ' ' && c != '-', "");
-    let path = dir.join(format!("{}.json", safe_name));
-
-    if !path.exists() {
-         return Err("Playlist not found".into());
-    }
-
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let mut playlist: Playlist = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-
-    // Avoid duplicates?
-    if !playlist.tracks.contains(&song_path) {
-        playlist.tracks.push(song_path);
-        
-        // Save back
-        let json = serde_json::to_string_pretty(&playlist).map_err(|e| e.to_string())?;
-        let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-        file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
-    }
-
-    Ok(playlist)
+    database::with_db(|conn| {
+        let Some(id) = playlist_id(conn, &playlist_name)? else { return Ok(None) };
+
+        // Avoid duplicates
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM playlist_tracks WHERE playlist_id = ?1 AND path = ?2)",
+            params![id, song_path],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute(
+                "INSERT INTO playlist_tracks (playlist_id, position, path)
+                 SELECT ?1, COALESCE(MAX(position) + 1, 0), ?2 FROM playlist_tracks WHERE playlist_id = ?1",
+                params![id, song_path],
+            )?;
+        }
+        load_playlist(conn, id).map(Some)
+    })?
+    .ok_or_else(|| "Playlist not found".into())
 }
 
 #[tauri::command]
 pub fn remove_from_playlist(playlist_name: String, song_path: String) -> Result<Playlist, String> {
-    let dir = get_playlist_dir();
-    let safe_name = playlist_name.replace(|c: char| !c.is_alphanumeric() && c != ' ' && c != '-', "");
-    let path = dir.join(format!("{}.json", safe_name));
-
-    if !path.exists() {
-        return Err("Playlist not found".into());
-    }
-
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let mut playlist: Playlist = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-
-    // Remove the track
-    if let Some(pos) = playlist.tracks.iter().position(|t| t == &song_path) {
-        playlist.tracks.remove(pos);
-        
-        // Save back
-        let json = serde_json::to_string_pretty(&playlist).map_err(|e| e.to_string())?;
-        let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-        file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
-    }
-
-    Ok(playlist)
+    database::with_db(|conn| {
+        let Some(id) = playlist_id(conn, &playlist_name)? else { return Ok(None) };
+
+        // Remove the track
+        conn.execute(
+            "DELETE FROM playlist_tracks WHERE playlist_id = ?1 AND position =
+             (SELECT MIN(position) FROM playlist_tracks WHERE playlist_id = ?1 AND path = ?2)",
+            params![id, song_path],
+        )?;
+        load_playlist(conn, id).map(Some)
+    })?
+    .ok_or_else(|| "Playlist not found".into())
 }
 
 #[tauri::command]
 pub fn delete_playlist(playlist_name: String) -> Result<(), String> {
-    let dir = get_playlist_dir();
-    let safe_name = playlist_name.replace(|c: char| !c.is_alphanumeric() && c != ' ' && c != '-', "");
-    let path = dir.join(format!("{}.json", safe_name));
+    let deleted = database::with_db(|conn| {
+        conn.execute("DELETE FROM playlists WHERE name = ?1", [&playlist_name])
+    })?;
 
-    if !path.exists() {
+    if deleted == 0 {
         return Err("Playlist not found".into());
     }
-
-    fs::remove_file(path).map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -0,0 +1,535 @@
+use serde::{Serialize, Deserialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    All,
+    One,
+}
+
+/// Snapshot of the queue sent with "queue-changed" and returned by `get_queue`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueueState {
+    pub tracks: Vec<String>,
+    pub current_index: Option<usize>,
+    // Play order as indices into `tracks`, only differs from it while shuffled
+    pub order: Vec<usize>,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+}
+
+/// The tracks to play and the order to play them in.
+///
+/// Shuffling draws a permutation of the queue up front rather than picking a random
+/// track each time, so nothing repeats until every track has played. Everything
+/// before the current entry in that order is the history `previous` walks back through.
+pub struct PlayQueue {
+    tracks: Vec<String>,
+    // Play order, a permutation of the indices into `tracks`
+    order: Vec<usize>,
+    // Position of the current track within `order`
+    cursor: Option<usize>,
+    // With shuffle and repeat all, the order after wrapping around. Drawn as soon as it
+    // is peeked at, so the preloaded track is the one that actually plays next.
+    next_cycle: Option<Vec<usize>>,
+    shuffle: bool,
+    repeat: RepeatMode,
+    rng: u64,
+}
+
+impl Default for PlayQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlayQueue {
+    pub fn new() -> Self {
+        Self {
+            tracks: Vec::new(),
+            order: Vec::new(),
+            cursor: None,
+            next_cycle: None,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            rng: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    pub fn state(&self) -> QueueState {
+        QueueState {
+            tracks: self.tracks.clone(),
+            current_index: self.current_index(),
+            order: self.order.clone(),
+            shuffle: self.shuffle,
+            repeat: self.repeat,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub fn current_index(&self) -> Option<usize> {
+        self.cursor.map(|c| self.order[c])
+    }
+
+    pub fn current(&self) -> Option<String> {
+        self.current_index().map(|i| self.tracks[i].clone())
+    }
+
+    pub fn index_of(&self, path: &str) -> Option<usize> {
+        self.tracks.iter().position(|t| t == path)
+    }
+
+    fn random(&mut self) -> u64 {
+        // xorshift64, plenty for picking play orders
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn random_below(&mut self, n: usize) -> usize {
+        (self.random() % n as u64) as usize
+    }
+
+    fn shuffle_slice(&mut self, items: &mut [usize]) {
+        for i in (1..items.len()).rev() {
+            let j = self.random_below(i + 1);
+            items.swap(i, j);
+        }
+    }
+
+    /// Rebuilds the play order around the current track, which plays first when shuffled.
+    fn reorder(&mut self) {
+        let current = self.current_index();
+        self.next_cycle = None;
+        self.order = (0..self.tracks.len()).collect();
+        if self.shuffle {
+            let mut rest: Vec<usize> = self.order.iter().copied().filter(|&i| Some(i) != current).collect();
+            self.shuffle_slice(&mut rest);
+            self.order = current.into_iter().chain(rest).collect();
+            self.cursor = current.map(|_| 0);
+        } else {
+            self.cursor = current;
+        }
+    }
+
+    /// A fresh shuffled order for the next time round, not starting with the track
+    /// that was just played.
+    fn draw_cycle(&mut self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.tracks.len()).collect();
+        self.shuffle_slice(&mut order);
+        if order.len() > 1 && Some(order[0]) == self.current_index() {
+            let j = 1 + self.random_below(order.len() - 1);
+            order.swap(0, j);
+        }
+        order
+    }
+
+    /// Replaces the queue. `start` becomes the current track, without one the first in
+    /// play order does, which is a random one when shuffled.
+    pub fn set_tracks(&mut self, tracks: Vec<String>, start: Option<usize>) {
+        self.tracks = tracks;
+        self.cursor = None;
+        self.reorder();
+        if self.tracks.is_empty() {
+            return;
+        }
+        match start.filter(|&i| i < self.tracks.len()) {
+            Some(index) => self.jump(index),
+            None => self.cursor = Some(0),
+        }
+    }
+
+    /// Puts back a queue saved earlier. A saved play order that doesn't fit the tracks
+    /// is drawn again.
+    pub fn restore(
+        &mut self,
+        tracks: Vec<String>,
+        order: Vec<usize>,
+        current_index: Option<usize>,
+        shuffle: bool,
+        repeat: RepeatMode,
+    ) {
+        self.tracks = tracks;
+        self.shuffle = shuffle;
+        self.repeat = repeat;
+        self.order = (0..self.tracks.len()).collect();
+        self.cursor = current_index.filter(|&i| i < self.tracks.len());
+        self.reorder();
+
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        if shuffle && sorted.iter().copied().eq(0..self.tracks.len()) {
+            let current = self.current_index();
+            self.cursor = current.and_then(|c| order.iter().position(|&i| i == c));
+            self.order = order;
+        }
+        if self.cursor.is_none() && !self.tracks.is_empty() {
+            self.cursor = Some(0);
+        }
+    }
+
+    /// The track that plays once the current one ends on its own, if any.
+    pub fn peek_next(&mut self) -> Option<String> {
+        let cursor = self.cursor?;
+        if self.repeat == RepeatMode::One {
+            return self.current();
+        }
+        if cursor + 1 < self.order.len() {
+            return Some(self.tracks[self.order[cursor + 1]].clone());
+        }
+        match self.repeat {
+            RepeatMode::All if self.shuffle => {
+                if self.next_cycle.is_none() {
+                    self.next_cycle = Some(self.draw_cycle());
+                }
+                self.next_cycle.as_ref().map(|order| self.tracks[order[0]].clone())
+            },
+            RepeatMode::All => Some(self.tracks[self.order[0]].clone()),
+            _ => None,
+        }
+    }
+
+    /// Moves on in play order. Past the end it wraps around when `wrap` is set, and
+    /// otherwise goes back to the start and returns None, as playback stops there.
+    fn step(&mut self, wrap: bool) -> Option<String> {
+        let cursor = self.cursor?;
+        if cursor + 1 < self.order.len() {
+            self.cursor = Some(cursor + 1);
+            return self.current();
+        }
+        if self.shuffle {
+            let order = match self.next_cycle.take() {
+                Some(order) => order,
+                None => self.draw_cycle(),
+            };
+            self.order = order;
+        }
+        self.cursor = Some(0);
+        if wrap {
+            self.current()
+        } else {
+            None
+        }
+    }
+
+    /// Moves on once the current track has ended: repeats it with repeat one, and
+    /// returns None at the end of the queue unless repeating all.
+    pub fn advance(&mut self) -> Option<String> {
+        match self.repeat {
+            RepeatMode::One => self.current(),
+            RepeatMode::All => self.step(true),
+            RepeatMode::Off => self.step(false),
+        }
+    }
+
+    /// Moves past a track that could not be played, even with repeat one.
+    pub fn skip(&mut self) -> Option<String> {
+        self.step(self.repeat != RepeatMode::Off)
+    }
+
+    /// The next button, which always moves on and wraps around at the end.
+    pub fn next(&mut self) -> Option<String> {
+        self.step(true)
+    }
+
+    /// The previous button, walking back through the play order and wrapping to its end.
+    pub fn previous(&mut self) -> Option<String> {
+        let cursor = self.cursor?;
+        self.cursor = Some(if cursor == 0 { self.order.len() - 1 } else { cursor - 1 });
+        self.next_cycle = None;
+        self.current()
+    }
+
+    /// Makes `index` the current track. When shuffled it is taken out of the play order
+    /// and placed right after the current one, so what played before stays the history.
+    pub fn jump(&mut self, index: usize) {
+        if index >= self.tracks.len() {
+            return;
+        }
+        self.next_cycle = None;
+        if !self.shuffle {
+            self.cursor = Some(index);
+            return;
+        }
+        let from = match self.order.iter().position(|&i| i == index) {
+            Some(from) => from,
+            None => return,
+        };
+        match self.cursor {
+            Some(cursor) if from <= cursor => {
+                // Already played this time round, play it again rather than rewinding
+                self.order.remove(from);
+                self.order.insert(cursor, index);
+            },
+            Some(cursor) => {
+                self.order.remove(from);
+                self.order.insert(cursor + 1, index);
+                self.cursor = Some(cursor + 1);
+            },
+            None => {
+                self.order.remove(from);
+                self.order.insert(0, index);
+                self.cursor = Some(0);
+            },
+        }
+    }
+
+    /// Remaps `order` after `tracks` changed, `old_to_new` giving each old index's new one.
+    fn remap(&mut self, old_to_new: &[Option<usize>]) {
+        let current = self.cursor.map(|c| self.order[c]);
+        let mut cursor = None;
+        let mut order = Vec::with_capacity(self.tracks.len());
+        for (position, &old) in self.order.iter().enumerate() {
+            if Some(position) == self.cursor {
+                cursor = Some(order.len());
+            }
+            if let Some(new) = old_to_new[old] {
+                order.push(new);
+            }
+        }
+        // A removed current track is replaced by whatever followed it
+        if cursor.is_some() && current.and_then(|c| old_to_new[c]).is_none() {
+            cursor = cursor.filter(|&c| c < order.len()).or(if order.is_empty() { None } else { Some(0) });
+        }
+        self.order = order;
+        self.cursor = cursor;
+        self.next_cycle = None;
+    }
+
+    /// Inserts `paths` to play right after the current track.
+    pub fn insert_next(&mut self, paths: Vec<String>) {
+        if paths.is_empty() {
+            return;
+        }
+        let at = self.current_index().map_or(self.tracks.len(), |i| i + 1);
+        let count = paths.len();
+        let old_to_new: Vec<Option<usize>> = (0..self.tracks.len())
+            .map(|i| Some(if i >= at { i + count } else { i }))
+            .collect();
+        self.tracks.splice(at..at, paths);
+        self.remap(&old_to_new);
+
+        let position = self.cursor.map_or(self.order.len(), |c| c + 1);
+        self.order.splice(position..position, at..at + count);
+        if self.cursor.is_none() {
+            self.cursor = Some(0);
+        }
+    }
+
+    /// Adds `paths` at the end of the queue. When shuffled they are mixed in among
+    /// the tracks still to play.
+    pub fn append(&mut self, paths: Vec<String>) {
+        let from = self.tracks.len();
+        self.tracks.extend(paths);
+        self.next_cycle = None;
+        for index in from..self.tracks.len() {
+            let position = if self.shuffle {
+                let first = self.cursor.map_or(0, |c| c + 1);
+                first + self.random_below(self.order.len() - first + 1)
+            } else {
+                self.order.len()
+            };
+            self.order.insert(position, index);
+        }
+        if self.cursor.is_none() && !self.tracks.is_empty() {
+            self.cursor = Some(0);
+        }
+    }
+
+    /// Moves the track at `from` to `to`. The play order follows when not shuffled.
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        if from >= self.tracks.len() || to >= self.tracks.len() || from == to {
+            return;
+        }
+        let mut indices: Vec<usize> = (0..self.tracks.len()).collect();
+        let moved = indices.remove(from);
+        indices.insert(to, moved);
+        let mut old_to_new = vec![None; self.tracks.len()];
+        for (new, &old) in indices.iter().enumerate() {
+            old_to_new[old] = Some(new);
+        }
+
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        self.remap(&old_to_new);
+        if !self.shuffle {
+            self.cursor = self.cursor.map(|c| self.order[c]);
+            self.order = (0..self.tracks.len()).collect();
+        }
+    }
+
+    /// Removes the track at `index`, returning whether it was the current one.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index >= self.tracks.len() {
+            return false;
+        }
+        let was_current = self.current_index() == Some(index);
+        let old_to_new: Vec<Option<usize>> = (0..self.tracks.len())
+            .map(|i| match i {
+                _ if i == index => None,
+                _ if i > index => Some(i - 1),
+                _ => Some(i),
+            })
+            .collect();
+        self.tracks.remove(index);
+        self.remap(&old_to_new);
+        was_current
+    }
+
+    /// Removes everything but the current track.
+    pub fn clear(&mut self) {
+        let current = self.current();
+        self.tracks = current.into_iter().collect();
+        self.order = (0..self.tracks.len()).collect();
+        self.cursor = if self.tracks.is_empty() { None } else { Some(0) };
+        self.next_cycle = None;
+    }
+
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        if shuffle != self.shuffle {
+            self.shuffle = shuffle;
+            self.reorder();
+        }
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn tracks(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("/music/{i}.flac")).collect()
+    }
+
+    fn queue(count: usize, start: Option<usize>) -> PlayQueue {
+        let mut queue = PlayQueue::new();
+        queue.set_tracks(tracks(count), start);
+        queue
+    }
+
+    #[test]
+    fn shuffle_plays_every_track_once_per_cycle() {
+        let mut queue = queue(20, Some(7));
+        queue.set_repeat(RepeatMode::All);
+        queue.set_shuffle(true);
+        assert_eq!(queue.current_index(), Some(7));
+
+        for _ in 0..5 {
+            let mut seen = HashSet::new();
+            seen.insert(queue.current().unwrap());
+            for _ in 1..20 {
+                assert!(seen.insert(queue.advance().unwrap()), "repeated within a cycle");
+            }
+            assert_eq!(seen.len(), 20);
+
+            // The preloaded track is the one that plays, and never the one just played
+            let last = queue.current();
+            let peeked = queue.peek_next();
+            assert_ne!(peeked, last);
+            assert_eq!(queue.advance(), peeked);
+        }
+    }
+
+    #[test]
+    fn previous_walks_back_through_shuffle_history() {
+        let mut queue = queue(10, Some(0));
+        queue.set_shuffle(true);
+        let played: Vec<String> = std::iter::once(queue.current().unwrap())
+            .chain((0..4).map(|_| queue.next().unwrap()))
+            .collect();
+        for expected in played.iter().rev().skip(1) {
+            assert_eq!(queue.previous().as_ref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn removing_current_track_moves_to_the_next() {
+        let mut queue = queue(4, Some(1));
+        assert!(queue.remove(1));
+        assert_eq!(queue.current().as_deref(), Some("/music/2.flac"));
+        assert_eq!(queue.len(), 3);
+
+        // Removing an earlier track keeps the current one
+        assert!(!queue.remove(0));
+        assert_eq!(queue.current().as_deref(), Some("/music/2.flac"));
+
+        // Removing the last track falls back to the start
+        let mut queue = self::queue(3, Some(2));
+        assert!(queue.remove(2));
+        assert_eq!(queue.current().as_deref(), Some("/music/0.flac"));
+
+        let mut queue = self::queue(1, Some(0));
+        assert!(queue.remove(0));
+        assert_eq!(queue.current(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn removing_current_track_while_shuffled() {
+        let mut queue = queue(8, Some(3));
+        queue.set_shuffle(true);
+        let next = queue.peek_next();
+        assert!(queue.remove(3));
+        assert_eq!(queue.current(), next);
+        assert_eq!(queue.state().order.len(), 7);
+    }
+
+    #[test]
+    fn moving_current_track_keeps_it_current() {
+        let mut queue = queue(5, Some(1));
+        queue.move_track(1, 4);
+        assert_eq!(queue.current_index(), Some(4));
+        assert_eq!(queue.current().as_deref(), Some("/music/1.flac"));
+        assert_eq!(queue.advance(), None);
+
+        let mut queue = self::queue(5, Some(3));
+        queue.move_track(0, 4);
+        assert_eq!(queue.current().as_deref(), Some("/music/3.flac"));
+        assert_eq!(queue.current_index(), Some(2));
+        assert_eq!(queue.advance().as_deref(), Some("/music/4.flac"));
+        assert_eq!(queue.advance().as_deref(), Some("/music/0.flac"));
+    }
+
+    #[test]
+    fn repeat_one_stays_on_the_track() {
+        let mut queue = queue(3, Some(2));
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(queue.peek_next().as_deref(), Some("/music/2.flac"));
+        assert_eq!(queue.advance().as_deref(), Some("/music/2.flac"));
+        // Skipping still moves on, wrapping around
+        assert_eq!(queue.next().as_deref(), Some("/music/0.flac"));
+        assert_eq!(queue.skip().as_deref(), Some("/music/1.flac"));
+    }
+
+    #[test]
+    fn repeat_all_wraps_around_and_off_stops() {
+        let mut queue = queue(3, Some(2));
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(queue.peek_next().as_deref(), Some("/music/0.flac"));
+        assert_eq!(queue.advance().as_deref(), Some("/music/0.flac"));
+        assert_eq!(queue.previous().as_deref(), Some("/music/2.flac"));
+
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(queue.peek_next(), None);
+        assert_eq!(queue.advance(), None);
+        // Playback stops back at the start of the queue
+        assert_eq!(queue.current().as_deref(), Some("/music/0.flac"));
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use lofty::tag::{ItemKey, Tag};
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+}
+
+/// Gain (dB) and peak (linear, 1.0 = full scale) values read from a file's tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+fn parse_number(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+/// iTunes stores its Sound Check data as ten hex words: the first two are the
+/// left/right adjustment in 1/1000 mW (relative to 1 mW), the 7th and 8th the peaks.
+fn parse_itunnorm(value: &str) -> (Option<f32>, Option<f32>) {
+    let words: Vec<u32> = value
+        .split_whitespace()
+        .filter_map(|w| u32::from_str_radix(w, 16).ok())
+        .collect();
+    if words.len() < 10 {
+        return (None, None);
+    }
+
+    let adjustment = words[0].max(words[1]);
+    let gain = (adjustment > 0).then(|| -10.0 * (adjustment as f32 / 1000.0).log10());
+    let peak = words[6].max(words[7]);
+    let peak = (peak > 0).then(|| peak as f32 / 32768.0);
+    (gain, peak)
+}
+
+impl ReplayGain {
+    pub fn from_tag(tag: &Tag) -> Self {
+        let mut gain = ReplayGain {
+            track_gain: tag.get_string(&ItemKey::ReplayGainTrackGain).and_then(parse_number),
+            track_peak: tag.get_string(&ItemKey::ReplayGainTrackPeak).and_then(parse_number),
+            album_gain: tag.get_string(&ItemKey::ReplayGainAlbumGain).and_then(parse_number),
+            album_peak: tag.get_string(&ItemKey::ReplayGainAlbumPeak).and_then(parse_number),
+        };
+
+        // Fall back to iTunes Sound Check for files that were only normalized by iTunes
+        if gain.track_gain.is_none() {
+            let itunnorm = tag.items().find_map(|item| match item.key() {
+                ItemKey::Unknown(key) if key.to_lowercase().ends_with("itunnorm") => item.value().text(),
+                _ => None,
+            });
+            if let Some(value) = itunnorm {
+                let (track_gain, track_peak) = parse_itunnorm(value);
+                gain.track_gain = track_gain;
+                gain.track_peak = gain.track_peak.or(track_peak);
+            }
+        }
+        gain
+    }
+
+    /// Linear factor for the given mode, lowered when needed so the peak never clips.
+    pub fn factor(&self, mode: ReplayGainMode, preamp_db: f32) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (
+                self.track_gain.or(self.album_gain),
+                self.track_peak.or(self.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                self.album_gain.or(self.track_gain),
+                self.album_peak.or(self.track_peak),
+            ),
+        };
+
+        let gain = match gain {
+            Some(gain) => gain,
+            None => return 1.0,
+        };
+        let factor = 10f32.powf((gain + preamp_db) / 20.0);
+        match peak {
+            Some(peak) if peak > 0.0 && peak * factor > 1.0 => 1.0 / peak,
+            _ => factor,
+        }
+    }
+}
+
+/// Linear gain shared with a playing `Gain` source, so it can change mid-track.
+#[derive(Clone)]
+pub struct GainHandle(Arc<AtomicU32>);
+
+impl GainHandle {
+    pub fn new(factor: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(factor.to_bits())))
+    }
+
+    pub fn set(&self, factor: f32) {
+        self.0.store(factor.to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+pub struct Gain<S> {
+    input: S,
+    handle: GainHandle,
+}
+
+impl<S: Source> Gain<S> {
+    pub fn new(input: S, handle: &GainHandle) -> Self {
+        Self {
+            input,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl<S: Source> Iterator for Gain<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        self.input.next().map(|sample| sample * self.handle.get())
+    }
+}
+
+impl<S: Source> Source for Gain<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
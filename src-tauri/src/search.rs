@@ -0,0 +1,660 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use crate::commands::Song;
+use crate::database;
+use crate::lyrics;
+
+// Which field of a document a term came from
+const TITLE: u8 = 1;
+const ARTIST: u8 = 2;
+const ALBUM: u8 = 4;
+const GENRE: u8 = 8;
+const LYRICS: u8 = 16;
+
+// Updates touching more songs than this rebuild the index instead, which is quicker
+const REBUILD_THRESHOLD: usize = 2000;
+pub const DEFAULT_RESULT_LIMIT: usize = 50;
+
+type AlbumKey = (String, Option<String>); // title, artist
+
+// Built on first use, then kept up to date as songs change
+static INDEX: RwLock<Option<SearchIndex>> = RwLock::new(None);
+// Bumped on every library change, so a build that was reading the library meanwhile
+// knows it may have missed one
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize)]
+pub struct AlbumResult {
+    pub title: String,
+    pub artist: Option<String>,
+    pub song_count: usize,
+    pub cover_handle: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtistResult {
+    pub name: String,
+    pub song_count: usize,
+}
+
+/// Best matches first within each group.
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub songs: Vec<Song>,
+    pub albums: Vec<AlbumResult>,
+    pub artists: Vec<ArtistResult>,
+}
+
+/// Lowercases and strips diacritics so "Beyoncé" finds "beyonce", then splits on anything
+/// that isn't a letter or digit. Apostrophes are dropped so "don't" stays one word.
+pub fn tokenize(text: &str) -> Vec<String> {
+    if text.is_ascii() {
+        return text
+            .split(|c: char| !c.is_ascii_alphanumeric() && c != '\'')
+            .map(|t| t.replace('\'', "").to_ascii_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+    }
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfkd() {
+        match c {
+            c if is_combining_mark(c) => {},
+            '\'' | '\u{2019}' => {},
+            // Letters that don't decompose into a base letter and a mark
+            'ß' => folded.push_str("ss"),
+            'æ' | 'Æ' => folded.push_str("ae"),
+            'œ' | 'Œ' => folded.push_str("oe"),
+            'ø' | 'Ø' => folded.push('o'),
+            'ł' | 'Ł' => folded.push('l'),
+            'đ' | 'Đ' => folded.push('d'),
+            'þ' | 'Þ' => folded.push_str("th"),
+            c => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()).map(String::from).collect()
+}
+
+/// Typos tolerated in a query word, longer words get more slack.
+fn allowed_typos(token: &str) -> usize {
+    match token.len() {
+        0..=3 => 0,
+        4..=7 => 1,
+        8..=MAX_FUZZY_LEN => 2,
+        _ => 0,
+    }
+}
+
+// Longer words are only matched exactly or by prefix
+const MAX_FUZZY_LEN: usize = 32;
+
+/// Edit distance counting a swap of neighbouring characters as one typo, or None once
+/// it exceeds `max`. Both words must be at most `MAX_FUZZY_LEN` bytes.
+fn typo_distance(a: &[u8], b: &[u8], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max || a.len() > MAX_FUZZY_LEN || b.len() > MAX_FUZZY_LEN {
+        return None;
+    }
+    // Three rows are enough, the swap looks two back
+    let mut before = [0u8; MAX_FUZZY_LEN + 1];
+    let mut previous = [0u8; MAX_FUZZY_LEN + 1];
+    let mut current = [0u8; MAX_FUZZY_LEN + 1];
+    for (j, cell) in previous.iter_mut().enumerate().take(b.len() + 1) {
+        *cell = j as u8;
+    }
+    for i in 1..=a.len() {
+        current[0] = i as u8;
+        let mut row_min = current[0];
+        for j in 1..=b.len() {
+            let cost = u8::from(a[i - 1] != b[j - 1]);
+            let mut value = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                value = value.min(before[j - 2] + 1);
+            }
+            current[j] = value;
+            row_min = row_min.min(value);
+        }
+        if row_min as usize > max {
+            return None;
+        }
+        before = previous;
+        previous = current;
+    }
+    Some(previous[b.len()] as usize).filter(|&d| d <= max)
+}
+
+/// Which bytes occur in a word, folded into 64 bits.
+fn letter_set(word: &[u8]) -> u64 {
+    word.iter().fold(0, |set, byte| set | 1 << (byte % 64))
+}
+
+/// Byte counts of a query word, folded into 64 buckets for `uncovered_letters`.
+fn letter_counts(word: &[u8]) -> [u8; 64] {
+    let mut counts = [0u8; 64];
+    for byte in word {
+        counts[(byte % 64) as usize] += 1;
+    }
+    counts
+}
+
+/// How many letters of the query word `term` has no counterpart for. Each typo accounts
+/// for at most one of them, and bytes sharing a bucket only make this lower, so like
+/// `letter_set` it can rule words out but never wrongly.
+fn uncovered_letters(mut counts: [u8; 64], token_len: usize, term: &[u8]) -> usize {
+    let mut covered = 0;
+    for byte in term {
+        let count = &mut counts[(byte % 64) as usize];
+        if *count > 0 {
+            *count -= 1;
+            covered += 1;
+        }
+    }
+    token_len - covered
+}
+
+/// Score multiplier for every combination of field bits a term can carry, counting only
+/// the fields being searched.
+fn field_weights(fields: u8) -> [f32; 32] {
+    let mut weights = [0.0; 32];
+    for (mask, weight) in weights.iter_mut().enumerate() {
+        *weight = [(TITLE, 1.0), (ARTIST, 0.9), (ALBUM, 0.8), (GENRE, 0.5), (LYRICS, 0.3)]
+            .iter()
+            .filter(|(field, _)| mask as u8 & fields & field != 0)
+            .map(|(_, weight)| *weight)
+            .fold(0.0, f32::max);
+    }
+    weights
+}
+
+struct Term {
+    letters: u64,         // letter_set of the word, to skip it cheaply when looking for typos
+    docs: Vec<(u32, u8)>, // document ids with the fields the word is in
+}
+
+/// Indexed words within a few typos of one query word, with how good a match each is.
+type TermMatches<'a> = Vec<(&'a str, f32)>;
+
+/// Inverted index from normalized words to the documents containing them. Documents get
+/// small ids internally so a search can score them in flat arrays.
+struct TokenIndex<K> {
+    terms: BTreeMap<String, Term>,
+    keys: Vec<Option<K>>,
+    ids: HashMap<K, u32>,
+    // Each document's terms, so it can be taken out again
+    doc_terms: Vec<Vec<String>>,
+    free: Vec<u32>,
+}
+
+impl<K> Default for TokenIndex<K> {
+    fn default() -> Self {
+        Self { terms: BTreeMap::new(), keys: Vec::new(), ids: HashMap::new(), doc_terms: Vec::new(), free: Vec::new() }
+    }
+}
+
+impl<K: Clone + Eq + Hash + Ord> TokenIndex<K> {
+    fn insert(&mut self, key: K, fields: &[(u8, &str)]) {
+        self.remove(&key);
+        let mut terms: HashMap<String, u8> = HashMap::new();
+        for (field, text) in fields {
+            for term in tokenize(text) {
+                *terms.entry(term).or_default() |= field;
+            }
+        }
+
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.keys.push(None);
+                self.doc_terms.push(Vec::new());
+                (self.keys.len() - 1) as u32
+            },
+        };
+        for (term, mask) in &terms {
+            match self.terms.get_mut(term) {
+                Some(entry) => entry.docs.push((id, *mask)),
+                None => {
+                    let entry = Term { letters: letter_set(term.as_bytes()), docs: vec![(id, *mask)] };
+                    self.terms.insert(term.clone(), entry);
+                },
+            }
+        }
+        self.keys[id as usize] = Some(key.clone());
+        self.doc_terms[id as usize] = terms.into_keys().collect();
+        self.ids.insert(key, id);
+    }
+
+    fn remove(&mut self, key: &K) {
+        let Some(id) = self.ids.remove(key) else { return };
+        for term in std::mem::take(&mut self.doc_terms[id as usize]) {
+            if let Some(entry) = self.terms.get_mut(&term) {
+                if let Some(position) = entry.docs.iter().position(|(doc, _)| *doc == id) {
+                    entry.docs.swap_remove(position);
+                }
+                if entry.docs.is_empty() {
+                    self.terms.remove(&term);
+                }
+            }
+        }
+        self.keys[id as usize] = None;
+        self.free.push(id);
+    }
+
+    /// Indexed words within a typo or two of a query word, not counting exact matches.
+    fn typo_matches(&self, token: &str) -> TermMatches<'_> {
+        let max = allowed_typos(token);
+        if max == 0 {
+            return Vec::new();
+        }
+        let letters = letter_set(token.as_bytes());
+        let counts = letter_counts(token.as_bytes());
+        let mut matches = Vec::new();
+        for (term, entry) in &self.terms {
+            if term.len().abs_diff(token.len()) > max
+                || (letters & !entry.letters).count_ones() as usize > max
+                || uncovered_letters(counts, token.len(), term.as_bytes()) > max
+            {
+                continue;
+            }
+            match typo_distance(token.as_bytes(), term.as_bytes(), max) {
+                Some(1) => matches.push((term.as_str(), 0.6)),
+                Some(2) => matches.push((term.as_str(), 0.4)),
+                _ => {},
+            }
+        }
+        matches
+    }
+
+    /// Documents where every query word matches, in one of `fields`, a whole word, the start
+    /// of one or one of its `typos`, best first. The typo matches may come from another
+    /// index, words this one lacks are skipped.
+    fn search(&self, tokens: &[String], typos: &[TermMatches], fields: u8, limit: usize) -> Vec<K> {
+        let weights = field_weights(fields);
+        // Zero means the document has missed a word so far
+        let mut totals = vec![0.0f32; self.keys.len()];
+        for (i, token) in tokens.iter().enumerate() {
+            let mut best = vec![0.0f32; self.keys.len()];
+            let mut score = |entry: &Term, quality: f32| {
+                for &(id, mask) in &entry.docs {
+                    let score = quality * weights[mask as usize];
+                    let slot = &mut best[id as usize];
+                    if score > *slot {
+                        *slot = score;
+                    }
+                }
+            };
+            // Words still being typed match what they start, single letters would match too much
+            if token.len() >= 2 {
+                for (term, entry) in self.terms.range::<str, _>((Bound::Included(token.as_str()), Bound::Unbounded)) {
+                    if !term.starts_with(token.as_str()) {
+                        break;
+                    }
+                    let quality = if term.len() == token.len() {
+                        1.0
+                    } else {
+                        0.5 + 0.3 * token.len() as f32 / term.len() as f32
+                    };
+                    score(entry, quality);
+                }
+            } else if let Some(entry) = self.terms.get(token) {
+                score(entry, 1.0);
+            }
+            for (term, quality) in typos.get(i).into_iter().flatten() {
+                if let Some(entry) = self.terms.get(*term) {
+                    score(entry, *quality);
+                }
+            }
+
+            let mut any = false;
+            for (total, best) in totals.iter_mut().zip(&best) {
+                *total = if *best > 0.0 && (i == 0 || *total > 0.0) { *total + best } else { 0.0 };
+                any |= *total > 0.0;
+            }
+            if !any {
+                return Vec::new();
+            }
+        }
+
+        let mut ranked: Vec<(&K, f32)> = totals
+            .iter()
+            .enumerate()
+            .filter(|(_, total)| **total > 0.0)
+            .filter_map(|(id, total)| Some((self.keys[id].as_ref()?, *total)))
+            .collect();
+        let by_rank = |a: &(&K, f32), b: &(&K, f32)| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0));
+        if ranked.len() > limit {
+            ranked.select_nth_unstable_by(limit, by_rank);
+            ranked.truncate(limit);
+        }
+        ranked.sort_by(by_rank);
+        ranked.into_iter().map(|(key, _)| key.clone()).collect()
+    }
+}
+
+struct AlbumEntry {
+    song_count: usize,
+    cover_handle: Option<String>,
+}
+
+#[derive(Default)]
+struct SearchIndex {
+    songs: TokenIndex<String>,
+    albums: TokenIndex<AlbumKey>,
+    artists: TokenIndex<String>,
+    // What each song counts towards in the album and artist documents
+    song_groups: HashMap<String, (Option<AlbumKey>, Option<String>)>,
+    album_entries: HashMap<AlbumKey, AlbumEntry>,
+    artist_songs: HashMap<String, usize>,
+}
+
+impl SearchIndex {
+    fn build() -> Result<Self, String> {
+        let songs = database::load_songs()?;
+        let saved_lyrics = lyrics::load_saved_lyrics()?;
+        let mut index = Self::default();
+        for song in &songs {
+            index.insert_song(song, saved_lyrics.get(&song.path).map(String::as_str));
+        }
+        Ok(index)
+    }
+
+    fn insert_song(&mut self, song: &Song, saved_lyrics: Option<&str>) {
+        self.remove_song(&song.path);
+
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let lyrics = [song.lyrics.as_deref(), saved_lyrics].iter().flatten().copied().collect::<Vec<_>>().join("\n");
+        self.songs.insert(
+            song.path.clone(),
+            &[
+                (TITLE, &text(&song.title)),
+                (ARTIST, &text(&song.artist)),
+                (ALBUM, &text(&song.album)),
+                (GENRE, &text(&song.genre)),
+                (LYRICS, &lyrics),
+            ],
+        );
+
+        let album = song.album.clone().map(|title| (title, song.artist.clone()));
+        if let Some(key) = &album {
+            let entry =
+                self.album_entries.entry(key.clone()).or_insert(AlbumEntry { song_count: 0, cover_handle: None });
+            entry.song_count += 1;
+            if entry.cover_handle.is_none() {
+                entry.cover_handle = song.cover_handle.clone();
+            }
+            if entry.song_count == 1 {
+                self.albums.insert(key.clone(), &[(ALBUM, &key.0), (ARTIST, &text(&key.1))]);
+            }
+        }
+        if let Some(artist) = &song.artist {
+            let count = self.artist_songs.entry(artist.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                self.artists.insert(artist.clone(), &[(ARTIST, artist)]);
+            }
+        }
+        self.song_groups.insert(song.path.clone(), (album, song.artist.clone()));
+    }
+
+    fn remove_song(&mut self, path: &str) {
+        let Some((album, artist)) = self.song_groups.remove(path) else { return };
+        self.songs.remove(&path.to_string());
+
+        if let Some(key) = album {
+            if let Some(entry) = self.album_entries.get_mut(&key) {
+                entry.song_count -= 1;
+                if entry.song_count == 0 {
+                    self.album_entries.remove(&key);
+                    self.albums.remove(&key);
+                }
+            }
+        }
+        if let Some(artist) = artist {
+            if let Some(count) = self.artist_songs.get_mut(&artist) {
+                *count -= 1;
+                if *count == 0 {
+                    self.artist_songs.remove(&artist);
+                    self.artists.remove(&artist);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the index ahead of the first search, so that one is quick too.
+pub fn warm_up() {
+    if let Err(e) = with_index(|_| ()) {
+        eprintln!("Failed to build search index: {}", e);
+    }
+}
+
+fn with_index<T>(f: impl FnOnce(&SearchIndex) -> T) -> Result<T, String> {
+    {
+        let index = INDEX.read().map_err(|_| "Failed to lock search index".to_string())?;
+        if let Some(index) = index.as_ref() {
+            return Ok(f(index));
+        }
+    }
+    loop {
+        let generation = GENERATION.load(Ordering::SeqCst);
+        let built = SearchIndex::build()?;
+        let mut index = INDEX.write().map_err(|_| "Failed to lock search index".to_string())?;
+        // An update that found no index to apply to came in while building, and the
+        // library it changed may already have been read, so read it again
+        if index.is_none() && GENERATION.load(Ordering::SeqCst) != generation {
+            continue;
+        }
+        // Another thread may have finished building first, either is current
+        return Ok(f(index.get_or_insert(built)));
+    }
+}
+
+/// Swaps in a freshly built index, searches keep using the old one meanwhile.
+fn rebuild() {
+    loop {
+        let generation = GENERATION.load(Ordering::SeqCst);
+        let built = match SearchIndex::build() {
+            Ok(built) => built,
+            Err(e) => {
+                eprintln!("Failed to rebuild search index: {}", e);
+                invalidate();
+                return;
+            },
+        };
+        let Ok(mut index) = INDEX.write() else { return };
+        // Changes made to the old index while building would be lost with it
+        if GENERATION.load(Ordering::SeqCst) == generation {
+            *index = Some(built);
+            return;
+        }
+    }
+}
+
+/// Brings the index up to date for songs that were added, changed or removed.
+pub(crate) fn update(paths: &[String]) {
+    if paths.is_empty() {
+        return;
+    }
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    if paths.len() > REBUILD_THRESHOLD {
+        rebuild();
+        return;
+    }
+
+    let songs = match database::load_songs_by_path(paths) {
+        Ok(songs) => songs,
+        Err(e) => {
+            eprintln!("Failed to update search index: {}", e);
+            invalidate();
+            return;
+        },
+    };
+    let saved_lyrics: HashMap<String, String> = songs
+        .iter()
+        .filter_map(|song| Some((song.path.clone(), lyrics::get_song_lyrics(song.path.clone()).ok()??)))
+        .collect();
+
+    let Ok(mut index) = INDEX.write() else { return };
+    // Not built yet, or still being built, which then notices the bumped generation
+    let Some(index) = index.as_mut() else { return };
+    for path in paths {
+        index.remove_song(path);
+    }
+    for song in &songs {
+        index.insert_song(song, saved_lyrics.get(&song.path).map(String::as_str));
+    }
+}
+
+/// Drops the index, the next search builds it again from the library.
+pub(crate) fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    if let Ok(mut index) = INDEX.write() {
+        *index = None;
+    }
+}
+
+/// Searches titles, artists, albums and genres, and lyrics when `include_lyrics` is set.
+/// Every word of the query has to match, as a whole word, the start of one, or with a
+/// typo or two in longer words.
+#[tauri::command]
+pub fn search_library(
+    query: String,
+    include_lyrics: Option<bool>,
+    limit: Option<usize>,
+) -> Result<SearchResults, String> {
+    let tokens = tokenize(&query);
+    let limit = limit.unwrap_or(DEFAULT_RESULT_LIMIT);
+    if tokens.is_empty() || limit == 0 {
+        return Ok(SearchResults { songs: Vec::new(), albums: Vec::new(), artists: Vec::new() });
+    }
+
+    let mut song_fields = TITLE | ARTIST | ALBUM | GENRE;
+    if include_lyrics.unwrap_or(false) {
+        song_fields |= LYRICS;
+    }
+    let (paths, albums, artists) = with_index(|index| {
+        // Songs hold every album and artist word too, so looking for typos once covers all three
+        let typos: Vec<TermMatches> = tokens.iter().map(|token| index.songs.typo_matches(token)).collect();
+        let albums: Vec<AlbumResult> = index
+            .albums
+            .search(&tokens, &typos, ALBUM | ARTIST, limit)
+            .into_iter()
+            .filter_map(|key| {
+                let entry = index.album_entries.get(&key)?;
+                Some(AlbumResult {
+                    title: key.0,
+                    artist: key.1,
+                    song_count: entry.song_count,
+                    cover_handle: entry.cover_handle.clone(),
+                })
+            })
+            .collect();
+        let artists: Vec<ArtistResult> = index
+            .artists
+            .search(&tokens, &typos, ARTIST, limit)
+            .into_iter()
+            .map(|name| ArtistResult { song_count: index.artist_songs.get(&name).copied().unwrap_or(0), name })
+            .collect();
+        (index.songs.search(&tokens, &typos, song_fields, limit), albums, artists)
+    })?;
+
+    // Songs come back in library order, put them back in ranked order
+    let rank: HashMap<&String, usize> = paths.iter().enumerate().map(|(i, path)| (path, i)).collect();
+    let mut songs = database::load_songs_by_path(&paths)?;
+    songs.sort_by_key(|song| rank.get(&song.path).copied().unwrap_or(usize::MAX));
+
+    Ok(SearchResults { songs, albums, artists })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &str, b: &str, max: usize) -> Option<usize> {
+        typo_distance(a.as_bytes(), b.as_bytes(), max)
+    }
+
+    #[test]
+    fn tokenize_folds_case_and_accents() {
+        assert_eq!(tokenize("Beyoncé - Crème Brûlée"), ["beyonce", "creme", "brulee"]);
+        assert_eq!(tokenize("Straße Ærø Łódź Þór"), ["strasse", "aero", "lodz", "thor"]);
+    }
+
+    #[test]
+    fn tokenize_drops_apostrophes_and_splits_on_punctuation() {
+        assert_eq!(tokenize("Don't Stop Me Now"), ["dont", "stop", "me", "now"]);
+        assert_eq!(tokenize("Don\u{2019}t Stop"), ["dont", "stop"]);
+        assert_eq!(tokenize("AC/DC - Back_in-Black (2009)"), ["ac", "dc", "back", "in", "black", "2009"]);
+        assert!(tokenize(" -- ").is_empty());
+    }
+
+    #[test]
+    fn tokenize_treats_ascii_and_unicode_text_alike() {
+        // Plain ASCII takes a shortcut past the Unicode folding
+        assert_eq!(tokenize("Rock'n'Roll Über"), ["rocknroll", "uber"]);
+        assert_eq!(tokenize("Rock'n'Roll Uber"), ["rocknroll", "uber"]);
+    }
+
+    #[test]
+    fn typo_distance_counts_edits() {
+        assert_eq!(distance("queen", "queen", 2), Some(0));
+        assert_eq!(distance("queen", "quen", 2), Some(1));
+        assert_eq!(distance("queen", "queens", 2), Some(1));
+        assert_eq!(distance("queen", "qaeen", 2), Some(1));
+        // Swapped neighbours are a single typo
+        assert_eq!(distance("queen", "qeuen", 2), Some(1));
+        assert_eq!(distance("radiohead", "raidohaed", 2), Some(2));
+    }
+
+    #[test]
+    fn typo_distance_gives_up_past_max() {
+        assert_eq!(distance("queen", "qeuen", 0), None);
+        assert_eq!(distance("beatles", "metallica", 2), None);
+        assert_eq!(distance("abc", "abcdef", 2), None);
+        let long = "a".repeat(MAX_FUZZY_LEN + 1);
+        assert_eq!(distance(&long, &long, 2), None);
+    }
+
+    #[test]
+    fn allowed_typos_grow_with_length() {
+        assert_eq!(allowed_typos("abc"), 0);
+        assert_eq!(allowed_typos("abcd"), 1);
+        assert_eq!(allowed_typos("abcdefgh"), 2);
+        assert_eq!(allowed_typos(&"a".repeat(MAX_FUZZY_LEN + 1)), 0);
+    }
+
+    #[test]
+    fn letter_filters_never_rule_out_a_match() {
+        let pairs =
+            [("queen", "qeuen"), ("metallica", "metalica"), ("radiohead", "raidohaed"), ("zzzzzzzz", "zzzzzzz")];
+        for (token, term) in pairs {
+            let max = distance(token, term, 2).unwrap();
+            let missing = letter_set(token.as_bytes()) & !letter_set(term.as_bytes());
+            assert!(missing.count_ones() as usize <= max, "{token} / {term}");
+            let uncovered = uncovered_letters(letter_counts(token.as_bytes()), token.len(), term.as_bytes());
+            assert!(uncovered <= max, "{token} / {term}");
+        }
+        assert!(uncovered_letters(letter_counts(b"zzzzzzzz"), 8, b"kalomine") > 2);
+    }
+
+    #[test]
+    fn search_needs_every_word_to_match() {
+        let mut index = TokenIndex::default();
+        index.insert("a", &[(TITLE, "Bohemian Rhapsody"), (ARTIST, "Queen")]);
+        index.insert("b", &[(TITLE, "Under Pressure"), (ARTIST, "Queen")]);
+        let search = |query: &str| {
+            let tokens = tokenize(query);
+            let typos: Vec<TermMatches> = tokens.iter().map(|token| index.typo_matches(token)).collect();
+            index.search(&tokens, &typos, TITLE | ARTIST, 10)
+        };
+
+        assert_eq!(search("queen"), ["a", "b"]);
+        assert_eq!(search("qeuen rhaps"), ["a"]);
+        assert!(search("queen thriller").is_empty());
+        // Single letters only match whole words
+        assert!(search("q").is_empty());
+    }
+}
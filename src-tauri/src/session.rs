@@ -0,0 +1,101 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::sync::Mutex;
+use crate::queue::RepeatMode;
+
+/// What was playing when the app last closed, restored paused on the next start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub tracks: Vec<String>,
+    // Play order as indices into `tracks`, so a shuffled queue resumes in the same order
+    pub order: Vec<usize>,
+    pub current_index: Option<usize>,
+    pub position_ms: u64,
+    pub volume: f32,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            tracks: Vec::new(),
+            order: Vec::new(),
+            current_index: None,
+            position_ms: 0,
+            volume: 0.5,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+        }
+    }
+}
+
+impl Session {
+    /// Drops tracks whose files have been deleted or moved since. When the current one
+    /// is gone, the next remaining track in play order takes its place from the start.
+    fn without_missing(mut self) -> Self {
+        let mut old_to_new = Vec::with_capacity(self.tracks.len());
+        let mut kept = 0;
+        for track in &self.tracks {
+            if Path::new(track).exists() {
+                old_to_new.push(Some(kept));
+                kept += 1;
+            } else {
+                old_to_new.push(None);
+            }
+        }
+        let mapped = |index: usize| old_to_new.get(index).copied().flatten();
+
+        let current = self.current_index.and_then(mapped);
+        if current.is_none() {
+            self.position_ms = 0;
+        }
+        self.current_index = current.or_else(|| {
+            let old = self.current_index?;
+            let position = self.order.iter().position(|&i| i == old).unwrap_or(0);
+            self.order.iter().skip(position).chain(self.order.iter()).find_map(|&i| mapped(i))
+        });
+        self.order = self.order.iter().filter_map(|&i| mapped(i)).collect();
+
+        let tracks = std::mem::take(&mut self.tracks);
+        self.tracks = tracks
+            .into_iter()
+            .zip(&old_to_new)
+            .filter_map(|(track, new)| new.map(|_| track))
+            .collect();
+        self
+    }
+}
+
+// Held while writing, so the audio thread and the exit save never interleave
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_session_path() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or(PathBuf::from("."));
+    path.push("luma");
+    let _ = fs::create_dir_all(&path);
+    path.push("session.json");
+    path
+}
+
+pub(crate) fn load_session() -> Option<Session> {
+    let content = fs::read_to_string(get_session_path()).ok()?;
+    let session: Session = serde_json::from_str(&content).ok()?;
+    Some(session.without_missing())
+}
+
+/// Writes to a temporary file first and renames it into place, so a crash mid-write
+/// leaves the previous session rather than a truncated one.
+pub(crate) fn write_session(session: &Session) -> Result<(), String> {
+    let json = serde_json::to_string(session).map_err(|e| e.to_string())?;
+    let _guard = WRITE_LOCK.lock().map_err(|_| "Failed to lock session file".to_string())?;
+    let path = get_session_path();
+    let temp = path.with_extension("json.tmp");
+    let mut file = fs::File::create(&temp).map_err(|e| e.to_string())?;
+    file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    fs::rename(&temp, &path).map_err(|e| e.to_string())
+}
@@ -2,12 +2,35 @@ use serde::{Serialize, Deserialize};
 use std::fs;
 use std::path::PathBuf;
 use std::io::Write;
+use tauri::State;
+use crate::player::AudioPlayer;
+use crate::replaygain::ReplayGainMode;
+use crate::equalizer::EqualizerSettings;
+use crate::silence::SilenceSettings;
+use crate::stereo::StereoSettings;
+use crate::visualizer::VisualizerSettings;
+use crate::watcher::LibraryWatcher;
 
-#[derive(Debug, Serialize, Deserialize)]
+// Fields missing from older settings files fall back to their defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub music_directory: String,
     pub theme: String, // light/dark
     pub seek_interval: u64,
+    pub crossfade_seconds: f32, // 0 disables, up to 12
+    pub replaygain_mode: ReplayGainMode,
+    pub replaygain_preamp_db: f32,
+    pub equalizer: EqualizerSettings,
+    pub output_device: Option<String>, // None follows the system default
+    pub playback_speed: f32, // 0.5 to 2.0
+    pub preserve_pitch: bool, // time-stretch instead of resampling when the speed isn't 1
+    pub visualizer: VisualizerSettings,
+    pub skip_silence: SilenceSettings,
+    pub stereo: StereoSettings,
+    pub transport_fade_ms: u32, // ramp on pause/resume/stop/seek, 0 disables
+    pub skip_unplayable: bool, // move on to the next track when one can't be opened
+    pub watch_library: bool, // pick up changes to the music directory without a manual sync
 }
 
 impl Default for AppSettings {
@@ -19,6 +42,19 @@ impl Default for AppSettings {
                 .to_string(),
             theme: "dark".to_string(),
             seek_interval: 10,
+            crossfade_seconds: 0.0,
+            replaygain_mode: ReplayGainMode::Off,
+            replaygain_preamp_db: 0.0,
+            equalizer: EqualizerSettings::default(),
+            output_device: None,
+            playback_speed: 1.0,
+            preserve_pitch: true,
+            visualizer: VisualizerSettings::default(),
+            skip_silence: SilenceSettings::default(),
+            stereo: StereoSettings::default(),
+            transport_fade_ms: 30,
+            skip_unplayable: true,
+            watch_library: true,
         }
     }
 }
@@ -43,12 +79,22 @@ pub fn load_settings() -> Result<AppSettings, String> {
     Ok(settings)
 }
 
-#[tauri::command]
-pub fn save_settings(settings: AppSettings) -> Result<(), String> {
+pub(crate) fn write_settings(settings: &AppSettings) -> Result<(), String> {
     let path = get_settings_path();
-    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
     
     let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
     file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
     Ok(())
 }
+
+#[tauri::command]
+pub fn save_settings(
+    player: State<'_, AudioPlayer>,
+    watcher: State<'_, LibraryWatcher>,
+    settings: AppSettings,
+) -> Result<(), String> {
+    write_settings(&settings)?;
+    watcher.apply_settings(&settings)?;
+    player.apply_settings(settings)
+}
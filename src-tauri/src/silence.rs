@@ -0,0 +1,315 @@
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+use crate::speed::SpeedHandle;
+
+// Silence kept on either side when a long pause mid-track is cut
+const PAD: Duration = Duration::from_millis(250);
+// Most input skipped per output sample, so a long silence can't stall the audio callback
+const MAX_SKIP: Duration = Duration::from_millis(250);
+// When only trimming, silence this close to the end is held back in case it runs into
+// it. Earlier pauses play through in real time once they pass the minimum.
+const TRAILING_WINDOW: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SilenceSettings {
+    pub trim: bool, // leading and trailing silence
+    pub skip_mid_track: bool, // long pauses anywhere, for spoken word
+    pub threshold_db: f32,
+    pub min_silence_ms: u32, // shorter pauses are always kept
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self {
+            trim: false,
+            skip_mid_track: false,
+            threshold_db: -50.0,
+            min_silence_ms: 1000,
+        }
+    }
+}
+
+/// Settings shared with every playing `SkipSilence` source.
+#[derive(Clone)]
+pub struct SilenceHandle {
+    trim: Arc<AtomicBool>,
+    skip_mid_track: Arc<AtomicBool>,
+    threshold: Arc<AtomicU32>,
+    min_silence_ms: Arc<AtomicU32>,
+}
+
+impl SilenceHandle {
+    pub fn new(settings: &SilenceSettings) -> Self {
+        let handle = Self {
+            trim: Arc::new(AtomicBool::new(false)),
+            skip_mid_track: Arc::new(AtomicBool::new(false)),
+            threshold: Arc::new(AtomicU32::new(0)),
+            min_silence_ms: Arc::new(AtomicU32::new(0)),
+        };
+        handle.set(settings);
+        handle
+    }
+
+    pub fn set(&self, settings: &SilenceSettings) {
+        let threshold = 10f32.powf(settings.threshold_db.clamp(-90.0, -20.0) / 20.0);
+        self.threshold.store(threshold.to_bits(), Ordering::Relaxed);
+        self.min_silence_ms.store(settings.min_silence_ms.clamp(250, 10000), Ordering::Relaxed);
+        self.trim.store(settings.trim, Ordering::Relaxed);
+        self.skip_mid_track.store(settings.skip_mid_track, Ordering::Relaxed);
+    }
+}
+
+/// How much audio a `SkipSilence` source has read but not yet played. It is still
+/// deciding whether to skip that audio, so the reported position must not include it.
+#[derive(Clone, Default)]
+pub struct HeldAudio(Arc<AtomicU64>);
+
+impl HeldAudio {
+    pub fn get(&self) -> Duration {
+        Duration::from_micros(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Drops silence below the threshold at the start of a track, at its end and, for spoken
+/// word, anywhere it lasts longer than the minimum. A silent run is held back until it is
+/// either broken by sound and played after all, or long enough (or final) to be skipped.
+/// Held audio past the minimum is only counted, and plays back as digital silence.
+pub struct SkipSilence<S> {
+    input: S,
+    handle: SilenceHandle,
+    held_audio: HeldAudio,
+    channels: usize,
+    sample_rate: SampleRate,
+    // The input plays at this speed, so how far it has read is counted in file time
+    speed: SpeedHandle,
+    position: f64,
+    output: VecDeque<Sample>,
+    // Digital silence played after the first `zeros_at` samples of `output`
+    zeros: u64,
+    zeros_at: usize,
+    // The current silent run: its first samples, then how many frames came after them
+    held: Vec<Sample>,
+    overflow: u64,
+    // Cutting a long run: the start of it to play if sound resumes, `held` rolls on
+    // with its latest samples
+    skipping: bool,
+    lead_out: Vec<Sample>,
+    // Only trimming, and the current silent run is too far from the end to be trailing
+    playing_through: bool,
+    // Sound has played since the track started
+    started: bool,
+    finished: bool,
+    frame: Vec<Sample>,
+}
+
+impl<S: Source> SkipSilence<S> {
+    pub fn new(input: S, handle: &SilenceHandle, held_audio: &HeldAudio, speed: &SpeedHandle) -> Self {
+        let channels = input.channels().max(1) as usize;
+        Self {
+            sample_rate: input.sample_rate(),
+            input,
+            handle: handle.clone(),
+            held_audio: held_audio.clone(),
+            channels,
+            speed: speed.clone(),
+            position: 0.0,
+            output: VecDeque::new(),
+            zeros: 0,
+            zeros_at: 0,
+            held: Vec::new(),
+            overflow: 0,
+            skipping: false,
+            lead_out: Vec::new(),
+            playing_through: false,
+            started: false,
+            finished: false,
+            frame: Vec::with_capacity(channels),
+        }
+    }
+
+    fn frames(&self, duration: Duration) -> usize {
+        (duration.as_secs_f64() * self.sample_rate as f64) as usize
+    }
+
+    fn publish_held(&self) {
+        let samples = (self.lead_out.len() + self.held.len()) as u64 + self.zeros;
+        let frames = samples / self.channels as u64 + self.overflow;
+        self.held_audio.0.store(frames * 1_000_000 / self.sample_rate.max(1) as u64, Ordering::Relaxed);
+    }
+
+    /// Plays the held run after all, as sound resumed before it counted as long. Only
+    /// called while nothing is waiting to be played.
+    fn release(&mut self) {
+        self.output.extend(self.lead_out.drain(..));
+        self.output.extend(self.held.drain(..));
+        self.zeros_at = self.output.len();
+        self.zeros = self.overflow * self.channels as u64;
+        self.overflow = 0;
+        self.skipping = false;
+    }
+
+    /// Whether the current silent run may still turn out to run to the end of the track.
+    /// Without a known length, only so much of it is held back before it plays.
+    fn may_be_trailing(&self) -> bool {
+        // The input's length is at the current speed, and changes with it
+        match self.input.total_duration() {
+            Some(total) => {
+                let remaining = total.as_secs_f64() - self.position / self.speed.speed() as f64;
+                remaining <= TRAILING_WINDOW.as_secs_f64()
+            },
+            None => self.overflow < self.frames(TRAILING_WINDOW) as u64,
+        }
+    }
+
+    /// Reads one frame and decides what happens to it. Returns false once the input ends.
+    fn step(&mut self) -> bool {
+        self.frame.clear();
+        self.frame.extend(self.input.by_ref().take(self.channels));
+        if self.frame.len() < self.channels {
+            // Trailing silence is dropped, anything else still held plays
+            if !self.handle.trim.load(Ordering::Relaxed) {
+                self.release();
+            }
+            self.lead_out.clear();
+            self.held.clear();
+            self.overflow = 0;
+            return false;
+        }
+        self.position += self.speed.speed() as f64 / self.sample_rate as f64;
+
+        let trim = self.handle.trim.load(Ordering::Relaxed);
+        let skip_mid_track = self.handle.skip_mid_track.load(Ordering::Relaxed);
+        let threshold = f32::from_bits(self.handle.threshold.load(Ordering::Relaxed));
+        let silent = (trim || skip_mid_track) && self.frame.iter().all(|s| s.abs() < threshold);
+
+        if !silent {
+            if self.skipping {
+                // Keep a little of the silence that was cut either side
+                let pad = self.frames(PAD) * self.channels;
+                let from = self.held.len().saturating_sub(pad);
+                self.output.extend(self.lead_out.drain(..));
+                self.output.extend(self.held.drain(from..));
+                self.held.clear();
+                self.skipping = false;
+            } else {
+                self.release();
+            }
+            self.started = true;
+            self.playing_through = false;
+            self.output.extend(self.frame.iter().copied());
+            return true;
+        }
+
+        if !self.started && trim {
+            return true;
+        }
+        if self.playing_through {
+            if !self.may_be_trailing() {
+                self.output.extend(self.frame.iter().copied());
+                return true;
+            }
+            // Got close to the end, so what follows may be trailing silence after all
+            self.playing_through = false;
+        }
+
+        let min_samples = self.frames(Duration::from_millis(
+            self.handle.min_silence_ms.load(Ordering::Relaxed) as u64,
+        )) * self.channels;
+        if self.skipping {
+            // Keep the most recent stretch around to lead back in with
+            self.held.extend(self.frame.iter().copied());
+            let pad = self.frames(PAD) * self.channels;
+            if self.held.len() > pad * 2 {
+                let excess = self.held.len() - pad;
+                self.held.drain(..excess);
+            }
+        } else if self.held.len() < min_samples {
+            self.held.extend(self.frame.iter().copied());
+        } else if skip_mid_track {
+            // Long enough to cut
+            let pad = (self.frames(PAD) * self.channels).min(self.held.len());
+            self.lead_out.clear();
+            self.lead_out.extend(self.held.drain(..pad));
+            self.held.clear();
+            self.overflow = 0;
+            self.skipping = true;
+        } else if self.may_be_trailing() {
+            // Only trimming: this may turn out to be the end of the track
+            self.overflow += 1;
+        } else {
+            // A pause within the track, which trimming keeps, so stop reading ahead
+            self.release();
+            self.playing_through = true;
+            self.output.extend(self.frame.iter().copied());
+        }
+        true
+    }
+}
+
+impl<S: Source> Iterator for SkipSilence<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let budget = self.frames(MAX_SKIP);
+        let mut read = 0;
+        while self.output.is_empty() && self.zeros == 0 && !self.finished {
+            if !self.step() {
+                self.finished = true;
+            }
+            read += 1;
+            if read >= budget && self.output.is_empty() && self.zeros == 0 && !self.finished {
+                // Skipping takes a little longer, rather than stalling the output
+                self.output.extend(std::iter::repeat_n(0.0, self.channels));
+            }
+        }
+        self.publish_held();
+        if self.zeros > 0 && self.zeros_at == 0 {
+            self.zeros -= 1;
+            return Some(0.0);
+        }
+        self.zeros_at = self.zeros_at.saturating_sub(1);
+        self.output.pop_front()
+    }
+}
+
+impl<S: Source> Source for SkipSilence<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.position = pos.as_secs_f64();
+        self.output.clear();
+        self.zeros = 0;
+        self.zeros_at = 0;
+        self.held.clear();
+        self.overflow = 0;
+        self.skipping = false;
+        self.lead_out.clear();
+        self.playing_through = false;
+        // Leading silence is only trimmed from the very start
+        self.started = !pos.is_zero();
+        self.finished = false;
+        self.publish_held();
+        Ok(())
+    }
+}
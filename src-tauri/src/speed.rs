@@ -0,0 +1,420 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+// Length of the fades either side of an A–B loop jump
+const LOOP_FADE: f64 = 0.005;
+
+/// Playback speed shared by every track the audio thread plays.
+#[derive(Clone)]
+pub struct SpeedHandle {
+    speed: Arc<AtomicU32>,
+    preserve_pitch: Arc<AtomicBool>,
+}
+
+impl SpeedHandle {
+    pub fn new(speed: f32, preserve_pitch: bool) -> Self {
+        let handle = Self {
+            speed: Arc::new(AtomicU32::new(1f32.to_bits())),
+            preserve_pitch: Arc::new(AtomicBool::new(preserve_pitch)),
+        };
+        handle.set(speed, preserve_pitch);
+        handle
+    }
+
+    pub fn set(&self, speed: f32, preserve_pitch: bool) {
+        let speed = if speed.is_finite() { speed.clamp(MIN_SPEED, MAX_SPEED) } else { 1.0 };
+        self.speed.store(speed.to_bits(), Ordering::Relaxed);
+        self.preserve_pitch.store(preserve_pitch, Ordering::Relaxed);
+    }
+
+    pub fn speed(&self) -> f32 {
+        f32::from_bits(self.speed.load(Ordering::Relaxed))
+    }
+
+    fn preserve_pitch(&self) -> bool {
+        self.preserve_pitch.load(Ordering::Relaxed)
+    }
+}
+
+/// Position within the file of what a `Speed` source is playing, which the sink's own
+/// position can't tell once output time and file time drift apart.
+#[derive(Clone, Default)]
+pub struct MediaClock(Arc<AtomicU64>);
+
+impl MediaClock {
+    pub fn get(&self) -> Duration {
+        Duration::from_micros(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, position: Duration) {
+        self.0.store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// A–B loop region of a single track, stored in microseconds. An end of zero means no loop.
+#[derive(Clone, Default)]
+pub struct LoopHandle {
+    start: Arc<AtomicU64>,
+    end: Arc<AtomicU64>,
+}
+
+impl LoopHandle {
+    pub fn set(&self, region: Option<(Duration, Duration)>) {
+        let (start, end) = region.map_or((0, 0), |(a, b)| (a.as_micros() as u64, b.as_micros() as u64));
+        // Disable first so a reader never pairs the new start with the old end
+        self.end.store(0, Ordering::Relaxed);
+        self.start.store(start, Ordering::Relaxed);
+        self.end.store(end, Ordering::Relaxed);
+    }
+
+    /// Start and end in seconds.
+    fn get(&self) -> Option<(f64, f64)> {
+        let end = self.end.load(Ordering::Relaxed);
+        let start = self.start.load(Ordering::Relaxed);
+        (end > start).then(|| (start as f64 / 1e6, end as f64 / 1e6))
+    }
+}
+
+/// Changes playback speed either by resampling, which shifts pitch along with tempo, or
+/// by WSOLA time-stretching, which keeps the pitch: windows of the input are overlap-added
+/// one output hop apart, each taken from wherever near its nominal position it lines up
+/// best with the previous window, so waveforms stay continuous across the seams.
+///
+/// Being the adapter that knows which part of the file is playing, it also runs the A–B
+/// loop: the input jumps back to A as it is read past B, with short fades either side.
+pub struct Speed<S> {
+    input: S,
+    handle: SpeedHandle,
+    ab_loop: LoopHandle,
+    clock: MediaClock,
+    channels: usize,
+    sample_rate: SampleRate,
+    // Interleaved input, the first frame being frame `origin` of everything read
+    buffer: Vec<Sample>,
+    origin: u64,
+    // File position at each frame the input was seeked or jumped to
+    timeline: VecDeque<(u64, Duration)>,
+    // Fading in after a jump back to A
+    loop_fade_in: bool,
+    // Read position in frames within `buffer`, the nominal analysis point when stretching
+    position: f64,
+    exhausted: bool,
+    output: VecDeque<Sample>,
+    stretch: Option<Stretch>,
+}
+
+/// WSOLA state: the window, its overlap-add accumulator and where the last window came from.
+struct Stretch {
+    window: Vec<f32>,
+    hop: usize,
+    tolerance: usize,
+    accumulator: Vec<Sample>,
+    previous: Option<usize>,
+}
+
+impl Stretch {
+    fn new(sample_rate: SampleRate, channels: usize) -> Self {
+        // ~20 ms windows at 50% overlap, searched ±5 ms for the best match
+        let hop = (sample_rate as usize / 100).max(32);
+        let length = hop * 2;
+        // A periodic Hann window sums to exactly one at 50% overlap
+        let window = (0..length)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / length as f32).cos())
+            .collect();
+        Self {
+            window,
+            hop,
+            tolerance: hop / 2,
+            accumulator: vec![0.0; length * channels],
+            previous: None,
+        }
+    }
+
+    fn length(&self) -> usize {
+        self.window.len()
+    }
+}
+
+impl<S: Source> Speed<S> {
+    pub fn new(input: S, handle: &SpeedHandle, ab_loop: &LoopHandle, clock: &MediaClock) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate();
+        clock.set(Duration::ZERO);
+        Self {
+            input,
+            handle: handle.clone(),
+            ab_loop: ab_loop.clone(),
+            clock: clock.clone(),
+            channels,
+            sample_rate,
+            buffer: Vec::new(),
+            origin: 0,
+            timeline: VecDeque::from([(0, Duration::ZERO)]),
+            loop_fade_in: false,
+            position: 0.0,
+            exhausted: false,
+            output: VecDeque::new(),
+            stretch: None,
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.buffer.len() / self.channels
+    }
+
+    /// Reads from the input until `frames` frames are buffered or it runs out.
+    fn fill(&mut self, frames: usize) -> bool {
+        while self.frames() < frames && !self.exhausted {
+            let start = self.buffer.len();
+            self.buffer.extend(self.input.by_ref().take(self.channels));
+            if self.buffer.len() - start < self.channels {
+                // Drop a trailing partial frame
+                self.exhausted = true;
+                self.buffer.truncate(start);
+                break;
+            }
+            self.apply_loop(start);
+        }
+        self.frames() >= frames
+    }
+
+    /// File position of the buffered frame at `index`, in seconds.
+    fn input_position(&self, index: usize) -> f64 {
+        let (frame, start) = self.timeline.back().copied().unwrap_or_default();
+        start.as_secs_f64() + (self.origin + index as u64 - frame) as f64 / self.sample_rate as f64
+    }
+
+    /// Fades the frame just read at `start` near the loop points, or drops it and jumps
+    /// back to A once the input has passed B.
+    fn apply_loop(&mut self, start: usize) {
+        let (a, b) = match self.ab_loop.get() {
+            Some(region) => region,
+            None => return,
+        };
+        let index = start / self.channels;
+        let position = self.input_position(index);
+
+        if position >= b {
+            self.buffer.truncate(start);
+            let jump = Duration::from_secs_f64(a);
+            match self.input.try_seek(jump) {
+                Ok(()) => {
+                    self.timeline.push_back((self.origin + index as u64, jump));
+                    self.loop_fade_in = true;
+                },
+                Err(e) => {
+                    eprintln!("A-B loop disabled, seek failed: {}", e);
+                    self.ab_loop.set(None);
+                },
+            }
+            return;
+        }
+
+        let mut gain = ((b - position) / LOOP_FADE).min(1.0);
+        if self.loop_fade_in {
+            let fade_in = (position - a) / LOOP_FADE;
+            if fade_in >= 1.0 {
+                self.loop_fade_in = false;
+            } else {
+                gain = gain.min(fade_in.max(0.0));
+            }
+        }
+        if gain < 1.0 {
+            for sample in &mut self.buffer[start..] {
+                *sample *= gain as f32;
+            }
+        }
+    }
+
+    /// Drops buffered frames that no future read can reach.
+    fn compact(&mut self, keep_from: usize) {
+        if keep_from < 4096 {
+            return;
+        }
+        self.buffer.drain(..keep_from * self.channels);
+        self.origin += keep_from as u64;
+        self.position -= keep_from as f64;
+        if let Some(stretch) = self.stretch.as_mut() {
+            stretch.previous = stretch.previous.map(|p| p - keep_from);
+        }
+    }
+
+    fn update_clock(&mut self) {
+        let current = self.origin as f64 + self.position;
+        while self.timeline.len() > 1 && self.timeline[1].0 as f64 <= current {
+            self.timeline.pop_front();
+        }
+        let (frame, start) = self.timeline[0];
+        let elapsed = (current - frame as f64).max(0.0) / self.sample_rate as f64;
+        self.clock.set(start + Duration::from_secs_f64(elapsed));
+    }
+
+    /// Linear interpolation between neighbouring frames, exact when the speed is 1.
+    fn resample(&mut self, speed: f64) -> bool {
+        let index = self.position.floor() as usize;
+        if !self.fill(index + 2) && self.frames() <= index {
+            return false;
+        }
+        let frac = (self.position - index as f64) as f32;
+        let has_next = self.frames() > index + 1;
+        for ch in 0..self.channels {
+            let a = self.buffer[index * self.channels + ch];
+            let sample = if has_next && frac > 0.0 {
+                let b = self.buffer[(index + 1) * self.channels + ch];
+                a + (b - a) * frac
+            } else {
+                a
+            };
+            self.output.push_back(sample);
+        }
+        self.position += speed;
+        self.compact(self.position.floor() as usize);
+        true
+    }
+
+    /// Picks the window start within the search range that best continues the previous
+    /// window, comparing channel sums over the overlapping half.
+    fn best_offset(&self, stretch: &Stretch, nominal: usize, available: usize) -> usize {
+        let previous = match stretch.previous {
+            Some(previous) => previous,
+            None => return nominal,
+        };
+        let natural = previous + stretch.hop;
+        let low = nominal.saturating_sub(stretch.tolerance);
+        let high = (nominal + stretch.tolerance).min(available.saturating_sub(stretch.length()));
+        // Too close to the end of the input to search
+        if high <= low || natural + stretch.hop > available {
+            return nominal;
+        }
+
+        let mono = |frame: usize| -> f32 {
+            self.buffer[frame * self.channels..(frame + 1) * self.channels].iter().sum()
+        };
+        let mut best = nominal.clamp(low, high);
+        let mut best_score = f32::MIN;
+        for candidate in low..=high {
+            // Every other frame is plenty to find the alignment
+            let mut score = 0.0;
+            let mut energy = 1e-9;
+            for i in (0..stretch.hop).step_by(2) {
+                let c = mono(candidate + i);
+                score += mono(natural + i) * c;
+                energy += c * c;
+            }
+            let score = score / energy.sqrt();
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+        best
+    }
+
+    /// Overlap-adds one window and moves a hop of finished output to the queue.
+    fn stretch_hop(&mut self, speed: f64) -> bool {
+        let mut stretch = match self.stretch.take() {
+            Some(stretch) => stretch,
+            None => Stretch::new(self.sample_rate, self.channels),
+        };
+        let length = stretch.length();
+        let nominal = self.position.round() as usize;
+        let needed = (nominal + stretch.tolerance + length).max(stretch.previous.map_or(0, |p| p + stretch.hop + length));
+        self.fill(needed);
+
+        let available = self.frames();
+        if self.exhausted && nominal >= available {
+            // Whatever is still in the accumulator is the tail of the last window
+            let tail = (stretch.hop * self.channels).min(stretch.accumulator.len());
+            self.output.extend(stretch.accumulator.drain(..tail));
+            stretch.accumulator.clear();
+            self.stretch = Some(stretch);
+            return !self.output.is_empty();
+        }
+
+        let offset = self.best_offset(&stretch, nominal, available);
+        for i in 0..length {
+            let weight = stretch.window[i];
+            for ch in 0..self.channels {
+                let sample = self.buffer.get((offset + i) * self.channels + ch).copied().unwrap_or(0.0);
+                stretch.accumulator[i * self.channels + ch] += sample * weight;
+            }
+        }
+        let hop = stretch.hop * self.channels;
+        self.output.extend(stretch.accumulator.drain(..hop));
+        stretch.accumulator.resize(length * self.channels, 0.0);
+        stretch.previous = Some(offset);
+        self.position += stretch.hop as f64 * speed;
+
+        let keep_from = offset.min(self.position as usize).saturating_sub(stretch.tolerance);
+        self.stretch = Some(stretch);
+        self.compact(keep_from);
+        true
+    }
+
+    fn produce(&mut self) -> bool {
+        let speed = self.handle.speed() as f64;
+        let stretching = self.handle.preserve_pitch() && speed != 1.0;
+        if !stretching && self.stretch.is_some() {
+            // Back to plain reads from where the stretch had got to
+            self.stretch = None;
+            self.position = self.position.round();
+        }
+
+        let produced = if stretching {
+            self.stretch_hop(speed)
+        } else {
+            self.resample(speed)
+        };
+        self.update_clock();
+        produced
+    }
+}
+
+impl<S: Source> Iterator for Speed<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.output.is_empty() && !self.produce() {
+            return None;
+        }
+        self.output.pop_front()
+    }
+}
+
+impl<S: Source> Source for Speed<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration().map(|d| d.div_f32(self.handle.speed()))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.buffer.clear();
+        self.output.clear();
+        self.origin = 0;
+        self.timeline = VecDeque::from([(0, pos)]);
+        self.loop_fade_in = false;
+        self.position = 0.0;
+        self.exhausted = false;
+        self.stretch = None;
+        self.clock.set(pos);
+        Ok(())
+    }
+}
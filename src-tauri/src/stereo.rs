@@ -0,0 +1,135 @@
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+
+// Per-frame smoothing factor, so changes glide over ~20 ms instead of clicking
+const SMOOTHING: f32 = 0.002;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StereoSettings {
+    pub balance: f32, // -1 (left only) to 1 (right only)
+    pub mono: bool,
+    pub swap_channels: bool,
+}
+
+/// Stereo settings shared with the `Stereo` stage on the output mix.
+#[derive(Clone)]
+pub struct StereoHandle {
+    balance: Arc<AtomicU32>,
+    mono: Arc<AtomicU32>,
+    swap: Arc<AtomicU32>,
+}
+
+impl StereoHandle {
+    pub fn new(settings: &StereoSettings) -> Self {
+        let handle = Self {
+            balance: Arc::new(AtomicU32::new(0)),
+            mono: Arc::new(AtomicU32::new(0)),
+            swap: Arc::new(AtomicU32::new(0)),
+        };
+        handle.set(settings);
+        handle
+    }
+
+    pub fn set(&self, settings: &StereoSettings) {
+        let balance = if settings.balance.is_finite() { settings.balance.clamp(-1.0, 1.0) } else { 0.0 };
+        self.balance.store(balance.to_bits(), Ordering::Relaxed);
+        self.mono.store(if settings.mono { 1f32 } else { 0f32 }.to_bits(), Ordering::Relaxed);
+        self.swap.store(if settings.swap_channels { 1f32 } else { 0f32 }.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Target (left gain, right gain, mono amount, swap amount).
+    fn targets(&self) -> [f32; 4] {
+        let balance = f32::from_bits(self.balance.load(Ordering::Relaxed));
+        [
+            (1.0 - balance).min(1.0),
+            (1.0 + balance).min(1.0),
+            f32::from_bits(self.mono.load(Ordering::Relaxed)),
+            f32::from_bits(self.swap.load(Ordering::Relaxed)),
+        ]
+    }
+}
+
+/// Balance, mono downmix and channel swap on the first two channels. Swapping and
+/// downmixing are blended in rather than switched, so toggling them doesn't click.
+pub struct Stereo<S> {
+    input: S,
+    handle: StereoHandle,
+    current: [f32; 4],
+    channel: ChannelCount,
+    right: Option<Sample>,
+}
+
+impl<S: Source> Stereo<S> {
+    pub fn new(input: S, handle: &StereoHandle) -> Self {
+        Self {
+            current: handle.targets(),
+            input,
+            handle: handle.clone(),
+            channel: 0,
+            right: None,
+        }
+    }
+
+    /// Processes a left/right pair, returning the left sample and keeping the right one.
+    fn process(&mut self, left: Sample, right: Sample) -> Sample {
+        let targets = self.handle.targets();
+        for (current, target) in self.current.iter_mut().zip(targets) {
+            *current += (target - *current) * SMOOTHING;
+        }
+        let [left_gain, right_gain, mono, swap] = self.current;
+
+        let (left, right) = (left + (right - left) * swap, right + (left - right) * swap);
+        let mid = (left + right) * 0.5;
+        let (left, right) = (left + (mid - left) * mono, right + (mid - right) * mono);
+        self.right = Some(right * right_gain);
+        left * left_gain
+    }
+}
+
+impl<S: Source> Iterator for Stereo<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let channels = self.input.channels();
+        let channel = self.channel;
+        self.channel = if channel + 1 >= channels { 0 } else { channel + 1 };
+
+        match channel {
+            _ if channels < 2 => self.input.next(),
+            0 => {
+                let left = self.input.next()?;
+                let right = self.input.next().unwrap_or(0.0);
+                Some(self.process(left, right))
+            },
+            1 => self.right.take(),
+            _ => self.input.next(),
+        }
+    }
+}
+
+impl<S: Source> Source for Stereo<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
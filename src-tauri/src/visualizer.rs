@@ -0,0 +1,291 @@
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use rodio::{Source, Sample, ChannelCount, SampleRate};
+use rodio::source::SeekError;
+
+const FFT_SIZE: usize = 2048;
+// Mono samples kept for the analyzer, anything older is dropped
+const CAPACITY: usize = FFT_SIZE * 2;
+// Samples collected on the audio path before taking the lock once
+const CHUNK: usize = 512;
+const WAVEFORM_SAMPLES: usize = 1024;
+const WAVEFORM_POINTS: usize = 256;
+// Band levels are scaled from this floor up to full scale
+const FLOOR_DB: f32 = -80.0;
+const MIN_FREQUENCY: f32 = 20.0;
+const MAX_FREQUENCY: f32 = 20000.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VisualizerSettings {
+    pub enabled: bool,
+    pub bands: usize, // 4 to 128
+    pub fps: u32, // 1 to 60
+}
+
+impl Default for VisualizerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bands: 32,
+            fps: 30,
+        }
+    }
+}
+
+/// One analyzer frame: band levels from 0 to 255 on a log frequency scale, low to
+/// high, and the most recent samples as -127 to 127.
+#[derive(Clone, Serialize)]
+struct VisualizerFrame {
+    bands: Vec<u8>,
+    waveform: Vec<i8>,
+}
+
+struct TapBuffer {
+    samples: VecDeque<f32>,
+    sample_rate: SampleRate,
+    // Bumped on every write so the analyzer can tell when nothing new arrived
+    written: u64,
+}
+
+/// Shared between the audio thread, whose output the `Tap` copies, and the analyzer thread.
+#[derive(Clone)]
+pub struct VisualizerHandle {
+    enabled: Arc<AtomicBool>,
+    settings: Arc<Mutex<VisualizerSettings>>,
+    buffer: Arc<Mutex<TapBuffer>>,
+}
+
+impl VisualizerHandle {
+    pub fn new(settings: VisualizerSettings) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(settings.enabled)),
+            settings: Arc::new(Mutex::new(settings)),
+            buffer: Arc::new(Mutex::new(TapBuffer {
+                samples: VecDeque::with_capacity(CAPACITY),
+                sample_rate: 44100,
+                written: 0,
+            })),
+        }
+    }
+
+    pub fn configure(&self, settings: VisualizerSettings) {
+        self.enabled.store(settings.enabled, Ordering::Relaxed);
+        if let Ok(mut current) = self.settings.lock() {
+            *current = settings;
+        }
+    }
+}
+
+/// Copies a mono mix of what plays into the analyzer buffer. The audio path never waits
+/// on the analyzer: a chunk is dropped if the buffer happens to be locked.
+///
+/// Wraps the output mixer, which ends whenever it has no inputs, so it plays silence
+/// instead of ending to stay attached to the stream.
+pub struct Tap<S> {
+    input: S,
+    enabled: Arc<AtomicBool>,
+    buffer: Arc<Mutex<TapBuffer>>,
+    channels: ChannelCount,
+    sample_rate: SampleRate,
+    chunk: Vec<f32>,
+    frame: f32,
+    channel: ChannelCount,
+}
+
+impl<S: Source> Tap<S> {
+    pub fn new(input: S, handle: &VisualizerHandle) -> Self {
+        Self {
+            channels: input.channels(),
+            sample_rate: input.sample_rate(),
+            input,
+            enabled: handle.enabled.clone(),
+            buffer: handle.buffer.clone(),
+            chunk: Vec::with_capacity(CHUNK),
+            frame: 0.0,
+            channel: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Ok(mut buffer) = self.buffer.try_lock() {
+            buffer.samples.extend(self.chunk.iter().copied());
+            let excess = buffer.samples.len().saturating_sub(CAPACITY);
+            buffer.samples.drain(..excess);
+            buffer.sample_rate = self.sample_rate;
+            buffer.written += 1;
+        }
+        self.chunk.clear();
+    }
+}
+
+impl<S: Source> Iterator for Tap<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let sample = self.input.next().unwrap_or(0.0);
+        if self.enabled.load(Ordering::Relaxed) {
+            self.frame += sample;
+            self.channel += 1;
+            if self.channel >= self.channels {
+                self.chunk.push(self.frame / self.channels as f32);
+                self.frame = 0.0;
+                self.channel = 0;
+                if self.chunk.len() >= CHUNK {
+                    self.flush();
+                }
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Tap<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+/// In-place iterative radix-2 FFT, `re` and `im` must have a power of two length.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f32::consts::PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn spectrum(samples: &[f32], sample_rate: SampleRate, bands: usize) -> Vec<u8> {
+    let n = samples.len();
+    let window: Vec<f32> = (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos())
+        .collect();
+    let mut re: Vec<f32> = samples.iter().zip(&window).map(|(s, w)| s * w).collect();
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+
+    // Scaled so a full-scale sine reads 0 dB
+    let scale = 2.0 / window.iter().sum::<f32>();
+    let magnitudes: Vec<f32> = (0..n / 2)
+        .map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt() * scale)
+        .collect();
+
+    let bin_width = sample_rate as f32 / n as f32;
+    let high = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+    let ratio = high / MIN_FREQUENCY;
+    (0..bands)
+        .map(|band| {
+            let from = MIN_FREQUENCY * ratio.powf(band as f32 / bands as f32);
+            let to = MIN_FREQUENCY * ratio.powf((band + 1) as f32 / bands as f32);
+            let first = ((from / bin_width).round() as usize).clamp(1, magnitudes.len() - 1);
+            // Low bands can be narrower than a bin, they still get the nearest one
+            let last = ((to / bin_width).round() as usize).clamp(first + 1, magnitudes.len());
+            let peak = magnitudes[first..last].iter().fold(0.0f32, |a, &b| a.max(b));
+            let db = 20.0 * peak.max(1e-9).log10();
+            (((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Keeps the sample furthest from zero in each group, so peaks survive the downsampling.
+fn waveform(samples: &[f32]) -> Vec<i8> {
+    samples
+        .chunks(samples.len().div_ceil(WAVEFORM_POINTS))
+        .map(|group| {
+            let peak = group.iter().copied().fold(0.0f32, |a, b| if b.abs() > a.abs() { b } else { a });
+            (peak.clamp(-1.0, 1.0) * 127.0).round() as i8
+        })
+        .collect()
+}
+
+/// Runs the analyzer on its own thread, emitting a "visualizer-frame" event per frame while
+/// enabled and something is playing. Slow consumers only delay this thread, never audio.
+pub fn spawn(app: AppHandle, handle: VisualizerHandle) {
+    thread::spawn(move || {
+        let mut last_written = 0;
+        let mut was_silent = false;
+        loop {
+            let settings = match handle.settings.lock() {
+                Ok(settings) => settings.clone(),
+                Err(_) => break,
+            };
+            if !settings.enabled {
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            thread::sleep(Duration::from_secs_f32(1.0 / settings.fps.clamp(1, 60) as f32));
+
+            let (samples, sample_rate) = match handle.buffer.lock() {
+                Ok(buffer) if buffer.written != last_written && buffer.samples.len() >= FFT_SIZE => {
+                    last_written = buffer.written;
+                    let skip = buffer.samples.len() - FFT_SIZE;
+                    (buffer.samples.iter().skip(skip).copied().collect::<Vec<f32>>(), buffer.sample_rate)
+                },
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+
+            // One empty frame when playback goes quiet, then nothing until it resumes
+            let silent = samples.iter().all(|s| s.abs() < 1e-6);
+            if silent && was_silent {
+                continue;
+            }
+            was_silent = silent;
+
+            let frame = VisualizerFrame {
+                bands: spectrum(&samples, sample_rate, settings.bands.clamp(4, 128)),
+                waveform: waveform(&samples[FFT_SIZE - WAVEFORM_SAMPLES..]),
+            };
+            let _ = app.emit("visualizer-frame", frame);
+        }
+    });
+}
@@ -0,0 +1,252 @@
+use std::thread;
+use std::sync::{mpsc, Mutex};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter};
+use crate::commands::{is_audio_file, refresh_library_paths};
+use crate::database;
+use crate::settings::{self, AppSettings};
+
+// Changes are applied once the directory has been quiet for this long, so a copied
+// album is read once it has finished arriving rather than file by file
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+// How often the music directory is checked for being unmounted or mounted again
+const MOUNT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+enum WatchCommand {
+    Configure { directory: PathBuf, enabled: bool },
+    Event(notify::Result<Event>),
+}
+
+/// Keeps the library cache in sync with the music directory while the app runs,
+/// emitting "library-changed" with what was added, updated or removed.
+pub struct LibraryWatcher {
+    sender: Mutex<mpsc::Sender<WatchCommand>>,
+}
+
+struct WatchThread {
+    app: AppHandle,
+    sender: mpsc::Sender<WatchCommand>,
+    directory: PathBuf,
+    enabled: bool,
+    watcher: Option<RecommendedWatcher>,
+    // Device of the directory when the watch started, to notice a different mount
+    mount: Option<u64>,
+    // The directory was a mount point of its own when last watched, so finding it on its
+    // parent's device means the drive is unmounted and only the bare mount point is left
+    mount_point: bool,
+    pending: HashSet<PathBuf>,
+    last_event: Option<Instant>,
+    last_mount_check: Instant,
+}
+
+/// The device the directory lives on and whether it differs from its parent's, which
+/// makes the directory a mount point. None while it doesn't exist.
+fn device_of(directory: &Path) -> Option<(u64, bool)> {
+    let metadata = fs::metadata(directory).ok()?;
+    if !metadata.is_dir() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let parent = directory.parent().and_then(|p| fs::metadata(p).ok());
+        Some((metadata.dev(), parent.is_some_and(|p| p.dev() != metadata.dev())))
+    }
+    #[cfg(not(unix))]
+    {
+        Some((0, false))
+    }
+}
+
+impl WatchThread {
+    fn new(app: AppHandle, sender: mpsc::Sender<WatchCommand>) -> Self {
+        Self {
+            app,
+            sender,
+            directory: PathBuf::new(),
+            enabled: false,
+            watcher: None,
+            mount: None,
+            mount_point: false,
+            pending: HashSet::new(),
+            last_event: None,
+            last_mount_check: Instant::now(),
+        }
+    }
+
+    fn handle(&mut self, command: WatchCommand) {
+        match command {
+            WatchCommand::Configure { directory, enabled } => {
+                if directory != self.directory || enabled != self.enabled {
+                    self.stop();
+                    self.mount_point = false;
+                    self.directory = directory;
+                    self.enabled = enabled;
+                    self.check_mount();
+                }
+            },
+            WatchCommand::Event(Ok(event)) => self.queue_event(event),
+            WatchCommand::Event(Err(e)) => eprintln!("Library watcher error: {}", e),
+        }
+    }
+
+    fn queue_event(&mut self, event: Event) {
+        if self.watcher.is_none() {
+            return;
+        }
+        // The backend dropped events, so only a look at the whole directory is reliable
+        if event.need_rescan() {
+            self.pending.insert(self.directory.clone());
+        } else if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        } else {
+            // Directories, and paths already gone, may hold audio files so they are kept too
+            let relevant = event
+                .paths
+                .into_iter()
+                .filter(|p| p.starts_with(&self.directory) && (is_audio_file(p) || !p.is_file()));
+            self.pending.extend(relevant);
+        }
+        self.last_event = Some(Instant::now());
+    }
+
+    fn next_wakeup(&self) -> Duration {
+        let mount_check = MOUNT_CHECK_INTERVAL.saturating_sub(self.last_mount_check.elapsed());
+        match self.last_event {
+            Some(last) if !self.pending.is_empty() => DEBOUNCE.saturating_sub(last.elapsed()).min(mount_check),
+            _ => mount_check,
+        }
+    }
+
+    fn poll(&mut self) {
+        if self.last_mount_check.elapsed() >= MOUNT_CHECK_INTERVAL {
+            self.check_mount();
+        }
+        if self.last_event.is_some_and(|last| last.elapsed() >= DEBOUNCE) {
+            self.flush();
+        }
+    }
+
+    /// Starts watching when the directory is available and stops when it goes away,
+    /// without touching the library for songs that are only out of reach.
+    fn check_mount(&mut self) {
+        self.last_mount_check = Instant::now();
+        if !self.enabled {
+            return;
+        }
+        let device = self.device();
+        if self.watcher.is_some() && device != self.mount {
+            eprintln!("Music directory {} is no longer available", self.directory.display());
+            self.stop();
+        }
+        if self.watcher.is_none() && device.is_some() && !self.looks_unmounted() {
+            self.start(device);
+        }
+    }
+
+    /// The device of the directory while it is available.
+    fn device(&self) -> Option<u64> {
+        let (device, mount_point) = device_of(&self.directory)?;
+        if self.mount_point && !mount_point {
+            return None;
+        }
+        Some(device)
+    }
+
+    /// Before anything has been watched there's no telling a bare mount point from a
+    /// folder the user emptied. The library still holding songs in an empty folder is
+    /// what a drive that wasn't mounted yet looks like, and watching would drop them all.
+    fn looks_unmounted(&self) -> bool {
+        if self.mount_point || fs::read_dir(&self.directory).map_or(true, |mut entries| entries.next().is_some()) {
+            return false;
+        }
+        database::has_songs_in(&self.directory).unwrap_or(false)
+    }
+
+    fn start(&mut self, mount: Option<u64>) {
+        let sender = self.sender.clone();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(WatchCommand::Event(event));
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Failed to create library watcher: {}", e);
+                return;
+            },
+        };
+        if let Err(e) = watcher.watch(&self.directory, RecursiveMode::Recursive) {
+            eprintln!("Failed to watch {}: {}", self.directory.display(), e);
+            return;
+        }
+        self.watcher = Some(watcher);
+        self.mount = mount;
+        self.mount_point = device_of(&self.directory).is_some_and(|(_, mount_point)| mount_point);
+        // Nothing was watched until now, whether the app was closed, the drive unmounted or
+        // the folder still empty, so look at all of it once
+        self.pending.insert(self.directory.clone());
+        self.last_event = Some(Instant::now());
+    }
+
+    fn stop(&mut self) {
+        self.watcher = None;
+        self.mount = None;
+        self.pending.clear();
+        self.last_event = None;
+    }
+
+    fn flush(&mut self) {
+        self.last_event = None;
+        let paths: Vec<PathBuf> = self.pending.drain().collect();
+        // Removals seen while the drive was being unmounted must not reach the library
+        if paths.is_empty() || self.device() != self.mount {
+            return;
+        }
+        match refresh_library_paths(&paths) {
+            Ok(changes) if !changes.is_empty() => {
+                let _ = self.app.emit("library-changed", changes);
+            },
+            Ok(_) => {},
+            Err(e) => eprintln!("Failed to update library: {}", e),
+        }
+    }
+}
+
+impl LibraryWatcher {
+    pub fn new(app: AppHandle) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let settings = settings::load_settings().unwrap_or_default();
+        let thread_tx = tx.clone();
+
+        thread::spawn(move || {
+            let mut watch = WatchThread::new(app, thread_tx);
+            loop {
+                match rx.recv_timeout(watch.next_wakeup()) {
+                    Ok(command) => watch.handle(command),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {},
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+                watch.poll();
+            }
+        });
+
+        let watcher = Self { sender: Mutex::new(tx) };
+        let _ = watcher.apply_settings(&settings);
+        watcher
+    }
+
+    pub fn apply_settings(&self, settings: &AppSettings) -> Result<(), String> {
+        self.sender
+            .lock()
+            .map_err(|_| "Failed to lock sender".to_string())?
+            .send(WatchCommand::Configure {
+                directory: PathBuf::from(&settings.music_directory),
+                enabled: settings.watch_library,
+            })
+            .map_err(|e| e.to_string())
+    }
+}
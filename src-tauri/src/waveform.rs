@@ -0,0 +1,138 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use rodio::Source;
+use crate::loudness::modified_secs;
+use crate::player::open_source;
+
+const DEFAULT_BUCKETS: usize = 1000;
+const MAX_BUCKETS: usize = 10000;
+// Frames summarized together while decoding, regrouped into buckets at the end
+const BLOCK_FRAMES: usize = 256;
+
+/// Overview of a whole track for the seek bar, levels scaled from 0 to 255.
+#[derive(Serialize, Deserialize)]
+pub struct Waveform {
+    pub duration_ms: u64,
+    pub peaks: Vec<u8>,
+    pub rms: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedWaveform {
+    modified: u64, // of the source file when this was computed
+    waveform: Waveform,
+}
+
+pub(crate) fn get_waveforms_dir() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or(PathBuf::from("."));
+    path.push("luma");
+    path.push("waveforms");
+    let _ = fs::create_dir_all(&path);
+    path
+}
+
+/// FNV-1a, which unlike the std hashers gives the same names on every build.
+fn path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn cache_path(path: &str, buckets: usize) -> PathBuf {
+    let mut cache = get_waveforms_dir();
+    cache.push(format!("{:016x}-{}.json", path_hash(path), buckets));
+    cache
+}
+
+/// Removes the cached overviews of `path` at other bucket counts that were computed
+/// from an older version of the file. Ones still current are kept.
+fn prune(path: &str, modified: u64, keep: &Path) {
+    // Only the modification time is needed, the levels are skipped over
+    #[derive(Deserialize)]
+    struct Modified {
+        modified: u64,
+    }
+
+    let prefix = format!("{:016x}-", path_hash(path));
+    let Ok(entries) = fs::read_dir(get_waveforms_dir()) else { return };
+    for entry in entries.flatten() {
+        let same_path = entry.file_name().to_str().is_some_and(|name| name.starts_with(&prefix));
+        if !same_path || entry.path() == keep {
+            continue;
+        }
+        let current = fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<Modified>(&content).ok())
+            .is_some_and(|cached| cached.modified == modified);
+        if !current {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn level(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn compute_waveform(path: &str, buckets: usize) -> Result<Waveform, String> {
+    let source = open_source(path)?;
+    let channels = source.channels().max(1) as usize;
+    let sample_rate = source.sample_rate();
+
+    // (peak, sum of squares, samples) per block
+    let mut blocks: Vec<(f32, f32, usize)> = Vec::new();
+    let mut current = (0.0f32, 0.0f32, 0usize);
+    for sample in source {
+        current.0 = current.0.max(sample.abs());
+        current.1 += sample * sample;
+        current.2 += 1;
+        if current.2 == BLOCK_FRAMES * channels {
+            blocks.push(current);
+            current = (0.0, 0.0, 0);
+        }
+    }
+    if current.2 > 0 {
+        blocks.push(current);
+    }
+
+    let total_samples: usize = blocks.iter().map(|b| b.2).sum();
+    let duration_ms = (total_samples / channels) as u64 * 1000 / sample_rate as u64;
+
+    let mut peaks = Vec::with_capacity(buckets);
+    let mut rms = Vec::with_capacity(buckets);
+    for bucket in 0..buckets {
+        // Tracks shorter than the bucket count repeat blocks rather than leave gaps
+        let from = bucket * blocks.len() / buckets;
+        let to = ((bucket + 1) * blocks.len() / buckets).max(from + 1).min(blocks.len());
+        let group = &blocks[from.min(to)..to];
+        let peak = group.iter().fold(0.0f32, |a, b| a.max(b.0));
+        let squares: f32 = group.iter().map(|b| b.1).sum();
+        let count: usize = group.iter().map(|b| b.2).sum();
+        peaks.push(level(peak));
+        rms.push(level(if count > 0 { (squares / count as f32).sqrt() } else { 0.0 }));
+    }
+
+    Ok(Waveform { duration_ms, peaks, rms })
+}
+
+#[tauri::command]
+pub async fn get_waveform(path: String, buckets: Option<usize>) -> Result<Waveform, String> {
+    let buckets = buckets.unwrap_or(DEFAULT_BUCKETS).clamp(1, MAX_BUCKETS);
+    let cache = cache_path(&path, buckets);
+    let modified = modified_secs(&path);
+    // Edited files get a fresh overview, replacing the old one
+    if let Some(cached) = fs::read_to_string(&cache)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CachedWaveform>(&content).ok())
+        .filter(|cached| cached.modified == modified)
+    {
+        return Ok(cached.waveform);
+    }
+
+    let cached = CachedWaveform { modified, waveform: compute_waveform(&path, buckets)? };
+    let json = serde_json::to_string(&cached).map_err(|e| e.to_string())?;
+    if let Err(e) = fs::write(&cache, json) {
+        eprintln!("Failed to cache waveform for {}: {}", path, e);
+    }
+    prune(&path, modified, &cache);
+    Ok(cached.waveform)
+}
@@ -1,8 +1,10 @@
 import { useState, useEffect } from "react";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { exit } from "@tauri-apps/plugin-process";
-import { Song } from "./models";
-import { usePlayer, usePlaylists, useLibrary, usePlaybackPersistence } from "./hooks";
+import { Song, SleepTimerState, SleepTimerStatus } from "./models";
+import { usePlayer, usePlaylists, useLibrary } from "./hooks";
 
 // Components
 import {
@@ -26,7 +28,7 @@ function App() {
   const [showPlayerPage, setShowPlayerPage] = useState(false);
   const [infoSong, setInfoSong] = useState<Song | null>(null);
   const [showExitConfirm, setShowExitConfirm] = useState(false);
-  const [sleepTimer, setSleepTimer] = useState<{ endTime: number, action: 'stop' | 'quit', originalDuration: number } | null>(null);
+  const [sleepTimer, setSleepTimer] = useState<SleepTimerState | null>(null);
 
   // Handle Window Close
   useEffect(() => {
@@ -43,16 +45,8 @@ function App() {
   // Wait, I need access to `handleConfirmExit` and playback controls.
   // I will check `usePlayer` content first in next step. For now I will hold off on this chunk.
 
+  // The backend saves the playback session as the app exits
   const handleConfirmExit = async () => {
-    try {
-      // Race saveState with a 1-second timeout
-      await Promise.race([
-        saveState(),
-        new Promise((resolve) => setTimeout(resolve, 1000))
-      ]);
-    } catch (e) {
-      console.error("Error saving state during exit:", e);
-    }
     await exit(0);
   };
 
@@ -68,6 +62,8 @@ function App() {
     scanMusic,
     handleClearCache,
     saveSettings,
+    watchLibrary,
+    setWatchLibrary,
   } = useLibrary();
 
   // Player Hook
@@ -75,19 +71,17 @@ function App() {
     queue,
     setQueue,
     currentIndex,
-    setCurrentIndex,
     currentSong,
     isPlaying,
+    setIsPlaying,
     currentTime,
-    setCurrentTime,
     volume,
-    setVolume,
     isShuffle,
-    setIsShuffle,
     loopMode,
-    setLoopMode,
-    playTrackInternal,
+    playIndex,
     playSong,
+    playNext,
+    addToQueue,
     togglePlay,
     toggleShuffle,
     toggleLoop,
@@ -110,42 +104,57 @@ function App() {
     setMenuOpenFor,
   } = usePlaylists({ currentSong });
 
-  // Playback Persistence Hook
-  const { saveState } = usePlaybackPersistence({
-    queue,
-    currentIndex,
-    currentTime,
-    volume,
-    isShuffle,
-    loopMode,
-    isPlaying,
-    setQueue,
-    setCurrentIndex,
-    setCurrentTime,
-    setVolume,
-    setIsShuffle,
-    setLoopMode,
-  });
-
-  // Handle Sleep Timer
+  // Sleep Timer runs in the backend, which pauses playback or quits when it fires
   useEffect(() => {
-    if (!sleepTimer) return;
+    const unlistenPromise = listen<{ action: 'stop' | 'quit' }>("sleep-timer-fired", () => {
+      setSleepTimer(null);
+      setIsPlaying(false);
+    });
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [setIsPlaying]);
 
-    const interval = setInterval(() => {
-      if (Date.now() >= sleepTimer.endTime) {
+  // A timer set before this window loaded is still running, and a track count goes
+  // down as tracks end
+  useEffect(() => {
+    invoke<SleepTimerStatus | null>("get_sleep_timer").then((status) => {
+      if (!status) {
         setSleepTimer(null);
-        if (sleepTimer.action === 'stop') {
-          if (isPlaying) {
-            togglePlay();
-          }
-        } else if (sleepTimer.action === 'quit') {
-          handleConfirmExit();
-        }
+      } else if (status.tracks_left !== null) {
+        setSleepTimer({ action: status.action, endTime: null, tracksLeft: status.tracks_left });
+      } else {
+        setSleepTimer({ action: status.action, endTime: Date.now() + (status.remaining_ms ?? 0), tracksLeft: null });
       }
-    }, 1000);
+    }).catch((e) => console.error("Failed to load sleep timer:", e));
+  }, [currentSong?.path]);
+
+  const handleSetSleepTimer = async (minutes: number, action: 'stop' | 'quit') => {
+    try {
+      await invoke("set_sleep_timer", { minutes, action });
+      setSleepTimer({
+        endTime: Date.now() + minutes * 60 * 1000,
+        action,
+        tracksLeft: null
+      });
+    } catch (e) {
+      console.error("Failed to set sleep timer:", e);
+    }
+  };
+
+  const handleSetSleepTimerTracks = async (tracks: number, action: 'stop' | 'quit') => {
+    try {
+      await invoke("set_sleep_timer_tracks", { tracks, action });
+      setSleepTimer({ endTime: null, action, tracksLeft: tracks });
+    } catch (e) {
+      console.error("Failed to set sleep timer:", e);
+    }
+  };
 
-    return () => clearInterval(interval);
-  }, [sleepTimer, isPlaying, togglePlay]);
+  const handleCancelSleepTimer = async () => {
+    setSleepTimer(null);
+    await invoke("cancel_sleep_timer").catch((e) => console.error("Failed to cancel sleep timer:", e));
+  };
 
   // Keyboard Shortcuts
   useEffect(() => {
@@ -204,26 +213,13 @@ function App() {
           onToggleLoop={toggleLoop}
           queue={queue}
           currentIndex={currentIndex}
-          onPlayIndex={async (index) => {
-            setCurrentIndex(index);
-            await playTrackInternal(queue[index].path);
-          }}
+          onPlayIndex={playIndex}
           isFavorite={isFavorite}
           onToggleFavorite={handleToggleFavorite}
-          sleepTimer={sleepTimer ? {
-            active: true,
-            endTime: sleepTimer.endTime,
-            action: sleepTimer.action,
-            originalDuration: sleepTimer.originalDuration
-          } : null}
-          onSetSleepTimer={(minutes, action) => {
-            setSleepTimer({
-              endTime: Date.now() + minutes * 60 * 1000,
-              action,
-              originalDuration: minutes
-            });
-          }}
-          onCancelSleepTimer={() => setSleepTimer(null)}
+          sleepTimer={sleepTimer ? { active: true, ...sleepTimer } : null}
+          onSetSleepTimer={handleSetSleepTimer}
+          onSetSleepTimerTracks={handleSetSleepTimerTracks}
+          onCancelSleepTimer={handleCancelSleepTimer}
         />
       )}
 
@@ -253,6 +249,8 @@ function App() {
                 loading={loading}
                 progress={syncProgress}
                 cacheSize={cacheSize}
+                watchLibrary={watchLibrary}
+                onWatchLibraryChange={setWatchLibrary}
               />
             </div>
           )}
@@ -288,6 +286,8 @@ function App() {
               playlists={playlists}
               menuOpenFor={menuOpenFor}
               onPlaySong={playSong}
+              onPlayNext={(song) => playNext([song])}
+              onAddToQueue={(song) => addToQueue([song])}
               onMenuToggle={setMenuOpenFor}
               onAddToPlaylist={addToPlaylist}
               onShowSongInfo={setInfoSong}
@@ -298,12 +298,15 @@ function App() {
           {currentView === "library" && (
             <Library
               songs={songs}
+              paged
               loading={loading}
               currentSong={currentSong}
               isPlaying={isPlaying}
               playlists={playlists}
               menuOpenFor={menuOpenFor}
               onPlaySong={playSong}
+              onPlayNext={(song) => playNext([song])}
+              onAddToQueue={(song) => addToQueue([song])}
               onMenuToggle={setMenuOpenFor}
               onAddToPlaylist={addToPlaylist}
               onShowSongInfo={setInfoSong}
@@ -333,20 +336,10 @@ function App() {
         onToggleLoop={toggleLoop}
         isFavorite={isFavorite}
         onToggleFavorite={handleToggleFavorite}
-        sleepTimer={sleepTimer ? {
-          active: true,
-          endTime: sleepTimer.endTime,
-          action: sleepTimer.action,
-          originalDuration: sleepTimer.originalDuration
-        } : null}
-        onSetSleepTimer={(minutes, action) => {
-          setSleepTimer({
-            endTime: Date.now() + minutes * 60 * 1000,
-            action,
-            originalDuration: minutes
-          });
-        }}
-        onCancelSleepTimer={() => setSleepTimer(null)}
+        sleepTimer={sleepTimer ? { active: true, ...sleepTimer } : null}
+        onSetSleepTimer={handleSetSleepTimer}
+        onSetSleepTimerTracks={handleSetSleepTimerTracks}
+        onCancelSleepTimer={handleCancelSleepTimer}
       />
 
       {/* SongInfoModal */}
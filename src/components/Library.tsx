@@ -1,15 +1,24 @@
 import { useState, useEffect, useRef, memo } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { Song, Playlist } from '../types';
+import { LibraryPage, SearchResults } from '../models';
 import { AlbumArt } from './AlbumArt';
 
+const PAGE_SIZE = 100;
+
 interface LibraryProps {
     songs: Song[];
+    // Pages the whole library in from the backend rather than showing `songs`, which
+    // then only tells the view when the library changed
+    paged?: boolean;
     loading: boolean;
     currentSong: Song | null;
     isPlaying: boolean;
     playlists: Playlist[];
     menuOpenFor: string | null;
     onPlaySong: (song: Song) => void;
+    onPlayNext: (song: Song) => void;
+    onAddToQueue: (song: Song) => void;
     onMenuToggle: (path: string | null) => void;
     onAddToPlaylist: (playlistName: string, songPath: string, keepOpen?: boolean) => void;
     onShowSongInfo: (song: Song) => void;
@@ -18,12 +27,15 @@ interface LibraryProps {
 
 export function Library({
     songs,
+    paged = false,
     loading,
     currentSong,
     isPlaying,
     playlists,
     menuOpenFor,
     onPlaySong,
+    onPlayNext,
+    onAddToQueue,
     onMenuToggle,
     onAddToPlaylist,
     onShowSongInfo,
@@ -31,25 +43,87 @@ export function Library({
 }: LibraryProps) {
     const [visibleCount, setVisibleCount] = useState(50);
     const [searchQuery, setSearchQuery] = useState('');
+    const [pageSongs, setPageSongs] = useState<Song[]>([]);
+    const [total, setTotal] = useState(0);
+    const [searchResults, setSearchResults] = useState<Song[] | null>(null);
     const loadMoreRef = useRef<HTMLDivElement>(null);
+    const requestRef = useRef(0);
 
     // Reset visible count when songs change (e.g. after a fresh sync)
     useEffect(() => {
         setVisibleCount(50);
     }, [songs.length]);
 
+    async function loadPage(offset: number, limit: number) {
+        const request = ++requestRef.current;
+        try {
+            const page = await invoke<LibraryPage>("query_library", { filter: {}, sort: [], offset, limit });
+            // A reload started since supersedes this page
+            if (request !== requestRef.current) return;
+            setPageSongs(prev => offset === 0 ? page.songs : [...prev.slice(0, offset), ...page.songs]);
+            setTotal(page.total);
+        } catch (e) {
+            console.error("Failed to load library page", e);
+        }
+    }
+
+    // Reload what is already shown whenever the library changes
+    useEffect(() => {
+        if (!paged) return;
+        loadPage(0, Math.max(pageSongs.length, PAGE_SIZE));
+    }, [paged, songs]);
+
+    useEffect(() => {
+        if (!paged) return;
+        const query = searchQuery.trim();
+        if (!query) {
+            setSearchResults(null);
+            return;
+        }
+        let cancelled = false;
+        const timeout = setTimeout(() => {
+            invoke<SearchResults>("search_library", { query, limit: 500 })
+                .then((results) => { if (!cancelled) setSearchResults(results.songs); })
+                .catch(e => console.error("Search failed", e));
+        }, 150);
+        return () => {
+            cancelled = true;
+            clearTimeout(timeout);
+        };
+    }, [paged, searchQuery, songs]);
+
+    const songCount = paged ? total : songs.length;
+    const visibleSongs = paged
+        ? searchResults ?? pageSongs
+        : songs
+            .filter(song => {
+                if (!searchQuery.trim()) return true;
+                const query = searchQuery.toLowerCase();
+                return (
+                    (song.title?.toLowerCase().includes(query)) ||
+                    (song.artist?.toLowerCase().includes(query)) ||
+                    (song.album?.toLowerCase().includes(query)) ||
+                    (song.path.toLowerCase().includes(query))
+                );
+            })
+            .slice(0, visibleCount);
+    const hasMore = paged ? !searchResults && pageSongs.length < total : visibleCount < songs.length;
+
     useEffect(() => {
         if (!loadMoreRef.current) return;
 
         const observer = new IntersectionObserver((entries) => {
-            if (entries[0].isIntersecting && visibleCount < songs.length) {
+            if (!entries[0].isIntersecting || !hasMore) return;
+            if (paged) {
+                loadPage(pageSongs.length, PAGE_SIZE);
+            } else {
                 setVisibleCount(prev => prev + 50);
             }
         }, { threshold: 0.1 });
 
         observer.observe(loadMoreRef.current);
         return () => observer.disconnect();
-    }, [visibleCount, songs.length]);
+    }, [visibleCount, songs.length, paged, pageSongs.length, hasMore]);
 
     return (
         <>
@@ -57,7 +131,7 @@ export function Library({
                 <div className="flex items-center justify-between gap-4">
                     <div>
                         <h1 className="text-3xl font-bold text-white tracking-tight drop-shadow-lg">Your Library</h1>
-                        <p className="text-white/30 text-sm mt-1 font-light">{songs.length} songs</p>
+                        <p className="text-white/30 text-sm mt-1 font-light">{songCount} songs</p>
                     </div>
                     <div className="relative flex-1 max-w-md">
                         <svg
@@ -93,7 +167,7 @@ export function Library({
                     <div className="flex items-center justify-center h-64">
                         <div className="animate-spin rounded-full h-10 w-10 border-b-2 border-white"></div>
                     </div>
-                ) : songs.length === 0 ? (
+                ) : songCount === 0 ? (
                     <div className="flex flex-col items-center justify-center h-64 text-white/40 gap-4">
                         <p className="font-light">No songs in your library</p>
                         <button
@@ -105,33 +179,23 @@ export function Library({
                     </div>
                 ) : (
                     <div className="grid grid-cols-1 gap-1 pb-32">
-                        {songs
-                            .filter(song => {
-                                if (!searchQuery.trim()) return true;
-                                const query = searchQuery.toLowerCase();
-                                return (
-                                    (song.title?.toLowerCase().includes(query)) ||
-                                    (song.artist?.toLowerCase().includes(query)) ||
-                                    (song.album?.toLowerCase().includes(query)) ||
-                                    (song.path.toLowerCase().includes(query))
-                                );
-                            })
-                            .slice(0, visibleCount)
-                            .map((song, idx) => (
-                                <SongRow
-                                    key={idx}
-                                    song={song}
-                                    isCurrent={currentSong?.path === song.path}
-                                    isPlaying={isPlaying}
-                                    menuOpen={menuOpenFor === song.path}
-                                    playlists={playlists}
-                                    onPlay={() => onPlaySong(song)}
-                                    onMenuToggle={() => onMenuToggle(menuOpenFor === song.path ? null : song.path)}
-                                    onAddToPlaylist={(playlistName, keepOpen) => onAddToPlaylist(playlistName, song.path, keepOpen)}
-                                    onShowInfo={() => onShowSongInfo(song)}
-                                />
-                            ))}
-                        {visibleCount < songs.length && (
+                        {visibleSongs.map((song, idx) => (
+                            <SongRow
+                                key={idx}
+                                song={song}
+                                isCurrent={currentSong?.path === song.path}
+                                isPlaying={isPlaying}
+                                menuOpen={menuOpenFor === song.path}
+                                playlists={playlists}
+                                onPlay={() => onPlaySong(song)}
+                                onPlayNext={() => onPlayNext(song)}
+                                onAddToQueue={() => onAddToQueue(song)}
+                                onMenuToggle={() => onMenuToggle(menuOpenFor === song.path ? null : song.path)}
+                                onAddToPlaylist={(playlistName, keepOpen) => onAddToPlaylist(playlistName, song.path, keepOpen)}
+                                onShowInfo={() => onShowSongInfo(song)}
+                            />
+                        ))}
+                        {hasMore && (
                             <div ref={loadMoreRef} className="h-20 flex items-center justify-center text-white/20 text-xs font-mono uppercase tracking-widest animate-pulse">
                                 Loading more tracks...
                             </div>
@@ -150,6 +214,8 @@ interface SongRowProps {
     menuOpen: boolean;
     playlists: Playlist[];
     onPlay: () => void;
+    onPlayNext: () => void;
+    onAddToQueue: () => void;
     onMenuToggle: () => void;
     onAddToPlaylist: (playlistName: string, keepOpen?: boolean) => void;
     onShowInfo: () => void;
@@ -162,6 +228,8 @@ const SongRow = memo(({
     menuOpen,
     playlists,
     onPlay,
+    onPlayNext,
+    onAddToQueue,
     onMenuToggle,
     onAddToPlaylist,
     onShowInfo,
@@ -216,7 +284,8 @@ const SongRow = memo(({
             </div>
 
             <div className="flex-1 min-w-0">
-                <h4 className={`font-medium truncate text-sm ${isCurrent ? "text-white" : "text-white/80"}`}>
+                <h4 className={`font-medium truncate text-sm ${isCurrent ? "text-white" : "text-white/80"} ${song.unplayable ? "line-through opacity-50" : ""}`}
+                    title={song.unplayable ? "This file couldn't be played" : undefined}>
                     {song.title || song.path.split('/').pop()}
                 </h4>
                 <p className="text-xs text-white/40 truncate group-hover:text-white/60 transition-colors">
@@ -263,6 +332,34 @@ const SongRow = memo(({
                             Song Info
                         </button>
 
+                        {/* Queue Options */}
+                        <button
+                            onClick={(e) => {
+                                e.stopPropagation();
+                                onPlayNext();
+                                onMenuToggle();
+                            }}
+                            className="w-full text-left px-4 py-3 text-sm text-white/80 hover:bg-white/10 hover:text-white transition-colors flex items-center gap-3 relative z-10"
+                        >
+                            <svg width="18" height="18" viewBox="0 0 24 24" fill="currentColor" className="text-white/50">
+                                <path d="M3 10h11v2H3v-2zm0-4h11v2H3V6zm0 8h7v2H3v-2zm13-1v8l6-4-6-4z" />
+                            </svg>
+                            Play Next
+                        </button>
+                        <button
+                            onClick={(e) => {
+                                e.stopPropagation();
+                                onAddToQueue();
+                                onMenuToggle();
+                            }}
+                            className="w-full text-left px-4 py-3 text-sm text-white/80 hover:bg-white/10 hover:text-white transition-colors flex items-center gap-3 border-b border-white/5 relative z-10"
+                        >
+                            <svg width="18" height="18" viewBox="0 0 24 24" fill="currentColor" className="text-white/50">
+                                <path d="M14 10H3v2h11v-2zm0-4H3v2h11V6zm4 8v-4h-2v4h-4v2h4v4h2v-4h4v-2h-4zM3 16h7v-2H3v2z" />
+                            </svg>
+                            Add to Queue
+                        </button>
+
                         {/* Add to Playlist Trigger - with + icon */}
                         <div className="relative">
                             <button
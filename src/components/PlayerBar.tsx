@@ -24,11 +24,12 @@ interface PlayerBarProps {
     onToggleFavorite: () => void;
     sleepTimer: {
         active: boolean;
-        endTime: number;
+        endTime: number | null;
+        tracksLeft: number | null;
         action: 'stop' | 'quit';
-        originalDuration?: number;
     } | null;
     onSetSleepTimer: (minutes: number, action: 'stop' | 'quit') => void;
+    onSetSleepTimerTracks: (tracks: number, action: 'stop' | 'quit') => void;
     onCancelSleepTimer: () => void;
 }
 
@@ -53,6 +54,7 @@ export function PlayerBar({
     onToggleFavorite,
     sleepTimer,
     onSetSleepTimer,
+    onSetSleepTimerTracks,
     onCancelSleepTimer,
 }: PlayerBarProps) {
     const [prevVolume, setPrevVolume] = useState(0.5);
@@ -201,10 +203,10 @@ export function PlayerBar({
                             <SleepTimerMenu
                                 onClose={() => setShowSleepMenu(false)}
                                 onSetTimer={onSetSleepTimer}
+                                onSetTrackTimer={onSetSleepTimerTracks}
                                 activeTimer={sleepTimer}
                                 onCancelTimer={onCancelSleepTimer}
                                 currentSongDuration={currentSong?.duration_seconds}
-                                currentTime={currentTime}
                             />
                         )}
                     </div>
@@ -27,11 +27,12 @@ interface PlayerPageProps {
     onSeekBackward: () => void;
     sleepTimer: {
         active: boolean;
-        endTime: number;
+        endTime: number | null;
+        tracksLeft: number | null;
         action: 'stop' | 'quit';
-        originalDuration?: number;
     } | null;
     onSetSleepTimer: (minutes: number, action: 'stop' | 'quit') => void;
+    onSetSleepTimerTracks: (tracks: number, action: 'stop' | 'quit') => void;
     onCancelSleepTimer: () => void;
 }
 
@@ -57,6 +58,7 @@ export function PlayerPage({
     onPlayIndex,
     sleepTimer,
     onSetSleepTimer,
+    onSetSleepTimerTracks,
     onCancelSleepTimer,
 }: PlayerPageProps) {
     const artSrc = useSongArt(currentSong);
@@ -165,10 +167,10 @@ export function PlayerPage({
                         <SleepTimerMenu
                             onClose={() => setShowSleepMenu(false)}
                             onSetTimer={onSetSleepTimer}
+                            onSetTrackTimer={onSetSleepTimerTracks}
                             activeTimer={sleepTimer}
                             onCancelTimer={onCancelSleepTimer}
                             currentSongDuration={currentSong.duration_seconds}
-                            currentTime={currentTime}
                             className="top-12 right-0"
                         />
                     )}
@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react';
 import { getVersion } from '@tauri-apps/api/app';
 import { useModal } from '../hooks/useModal';
+import { SyncProgress } from '../models';
 
 
 interface SettingsProps {
@@ -10,11 +11,13 @@ interface SettingsProps {
     scanMusic: () => void;
     onClearCache: () => void;
     loading: boolean;
-    progress: { current: number; total: number } | null;
+    progress: SyncProgress | null;
     cacheSize: number;
+    watchLibrary: boolean;
+    onWatchLibraryChange: (enabled: boolean) => void;
 }
 
-export function Settings({ path, seekInterval, onSave, scanMusic, onClearCache, loading, progress, cacheSize }: SettingsProps) {
+export function Settings({ path, seekInterval, onSave, scanMusic, onClearCache, loading, progress, cacheSize, watchLibrary, onWatchLibraryChange }: SettingsProps) {
     const { showConfirm } = useModal();
     const [localPath, setLocalPath] = useState(path);
     const [localSeek, setLocalSeek] = useState(seekInterval);
@@ -29,7 +32,8 @@ export function Settings({ path, seekInterval, onSave, scanMusic, onClearCache,
         onSave(localPath, localSeek);
     };
 
-    const percent = progress ? Math.round((progress.current / progress.total) * 100) : 0;
+    // Nothing to read means everything was already up to date
+    const percent = progress ? (progress.total > 0 ? Math.round((progress.current / progress.total) * 100) : 100) : 0;
 
     const formatSize = (bytes: number) => {
         if (bytes === 0) return '0 Bytes';
@@ -98,8 +102,9 @@ export function Settings({ path, seekInterval, onSave, scanMusic, onClearCache,
                                     </div>
                                 </div>
                                 {progress && (
-                                    <div className="text-right text-[10px] font-mono text-white/30">
-                                        {percent}% Complete
+                                    <div className="flex justify-between text-[10px] font-mono text-white/30">
+                                        <span>+{progress.added} new · {progress.updated} changed · -{progress.removed} removed</span>
+                                        <span>{percent}% Complete</span>
                                     </div>
                                 )}
                             </div>
@@ -107,6 +112,22 @@ export function Settings({ path, seekInterval, onSave, scanMusic, onClearCache,
                     </div>
                 </div>
 
+                {/* Watch Folder */}
+                <div className="bg-white/5 border border-white/10 rounded-xl p-4">
+                    <div className="flex items-center justify-between">
+                        <div>
+                            <h4 className="font-medium text-white text-sm">Watch Folder</h4>
+                            <p className="text-xs text-white/40">Add new and changed files without syncing</p>
+                        </div>
+                        <button
+                            onClick={() => onWatchLibraryChange(!watchLibrary)}
+                            className={`w-11 h-6 rounded-full relative transition-colors ${watchLibrary ? 'bg-white' : 'bg-white/20'}`}
+                        >
+                            <div className={`absolute top-0.5 left-0.5 w-5 h-5 rounded-full transition-transform shadow ${watchLibrary ? 'translate-x-5 bg-black' : 'translate-x-0 bg-white'}`}></div>
+                        </button>
+                    </div>
+                </div>
+
                 {/* Seek Interval */}
                 <div className="bg-white/5 border border-white/10 rounded-xl p-4">
                     <div className="flex items-center justify-between">
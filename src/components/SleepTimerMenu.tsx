@@ -3,27 +3,28 @@ import { useState, useEffect, useRef } from 'react';
 interface SleepTimerMenuProps {
     onClose: () => void;
     onSetTimer: (minutes: number, action: 'stop' | 'quit') => void;
+    onSetTrackTimer: (tracks: number, action: 'stop' | 'quit') => void;
     activeTimer: {
-        endTime: number;
+        endTime: number | null;
+        tracksLeft: number | null;
         action: 'stop' | 'quit';
-        originalDuration?: number;
     } | null;
     onCancelTimer: () => void;
     currentSongDuration?: number;
-    currentTime?: number;
     className?: string;
 }
 
 export function SleepTimerMenu({
     onClose,
     onSetTimer,
+    onSetTrackTimer,
     activeTimer,
     onCancelTimer,
     currentSongDuration,
-    currentTime,
     className = "bottom-24 right-8"
 }: SleepTimerMenuProps) {
     const [customMinutes, setCustomMinutes] = useState('');
+    const [customUnit, setCustomUnit] = useState<'minutes' | 'tracks'>('minutes');
     const [action, setAction] = useState<'stop' | 'quit'>('stop');
     const menuRef = useRef<HTMLDivElement>(null);
 
@@ -44,22 +45,21 @@ export function SleepTimerMenu({
     };
 
     const handleCustomSet = () => {
-        const mins = parseInt(customMinutes);
-        if (!isNaN(mins) && mins > 0) {
-            onSetTimer(mins, action);
+        const count = parseInt(customMinutes);
+        if (!isNaN(count) && count > 0) {
+            if (customUnit === 'tracks') {
+                onSetTrackTimer(count, action);
+            } else {
+                onSetTimer(count, action);
+            }
             onClose();
         }
     };
 
+    // Counted in tracks, so it still lands on the end after a seek or a speed change
     const handleEndOfSong = () => {
-        if (currentSongDuration && currentTime !== undefined) {
-            const remaining = Math.max(0, currentSongDuration - currentTime);
-            // Convert seconds to minutes for consistency, or pass precise seconds?
-            // The prop expects minutes, but for better precision we might want to handle it.
-            // For now, let's pass minutes as float.
-            onSetTimer(remaining / 60, action);
-            onClose();
-        }
+        onSetTrackTimer(1, action);
+        onClose();
     };
 
     return (
@@ -133,10 +133,17 @@ export function SleepTimerMenu({
                     type="number"
                     value={customMinutes}
                     onChange={(e) => setCustomMinutes(e.target.value)}
-                    placeholder="Custom (min)"
+                    placeholder={customUnit === 'tracks' ? "Custom (songs)" : "Custom (min)"}
                     className="flex-1 bg-black/20 border border-white/10 rounded-lg px-3 py-1.5 text-xs text-white focus:outline-none focus:border-white/30 placeholder:text-white/20"
                     onKeyDown={(e) => e.key === 'Enter' && handleCustomSet()}
                 />
+                <button
+                    onClick={() => setCustomUnit(customUnit === 'minutes' ? 'tracks' : 'minutes')}
+                    className="px-2 bg-white/5 hover:bg-white/10 text-white/60 hover:text-white rounded-lg text-xs transition-colors"
+                    title="Count minutes or songs"
+                >
+                    {customUnit === 'tracks' ? 'songs' : 'min'}
+                </button>
                 <button
                     onClick={handleCustomSet}
                     disabled={!customMinutes}
@@ -151,7 +158,11 @@ export function SleepTimerMenu({
                 <div className="mt-4 pt-3 border-t border-white/10 text-center">
                     <p className="text-xs text-white/40 mb-1">Timer Active</p>
                     <p className="text-sm font-mono text-blue-400">
-                        Ends in {Math.ceil((activeTimer.endTime - Date.now()) / 60000)} min
+                        {activeTimer.tracksLeft !== null
+                            ? activeTimer.tracksLeft === 1
+                                ? "Ends after this song"
+                                : `Ends after ${activeTimer.tracksLeft} songs`
+                            : `Ends in ${Math.ceil(((activeTimer.endTime ?? Date.now()) - Date.now()) / 60000)} min`}
                     </p>
                 </div>
             )}
@@ -1,6 +1,5 @@
 export { usePlayer } from "./usePlayer";
 export { usePlaylists } from "./usePlaylists";
 export { useLibrary } from "./useLibrary";
-export { usePlaybackPersistence } from "./usePlaybackPersistence";
 export { useModal } from "./useModal";
 
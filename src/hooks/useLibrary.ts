@@ -1,7 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import { Song, AppSettings, UseLibraryReturn } from "../models";
+import { Song, AppSettings, LibraryChanges, ScanResult, SyncProgress, UseLibraryReturn } from "../models";
 import { useModal } from "./useModal";
 
 export function useLibrary(): UseLibraryReturn {
@@ -10,14 +10,16 @@ export function useLibrary(): UseLibraryReturn {
     const [loading, setLoading] = useState(false);
     const [path, setPath] = useState("");
     const [seekInterval, setSeekInterval] = useState(10);
-    const [syncProgress, setSyncProgress] = useState<{ current: number; total: number } | null>(null);
+    const [syncProgress, setSyncProgress] = useState<SyncProgress | null>(null);
     const [cacheSize, setCacheSize] = useState<number>(0);
+    const [watchLibrary, setWatchLibraryState] = useState(true);
 
     useEffect(() => {
         async function init() {
             const settings = await invoke<AppSettings>("load_settings");
             setPath(settings.music_directory);
             setSeekInterval(settings.seek_interval || 10);
+            setWatchLibraryState(settings.watch_library ?? true);
 
             setLoading(true);
             try {
@@ -40,14 +42,36 @@ export function useLibrary(): UseLibraryReturn {
 
     useEffect(() => {
         let unlisten: any;
+        let unlistenUnplayable: any;
+        let unlistenChanges: any;
         const setup = async () => {
-            unlisten = await listen<{ current: number; total: number }>("sync-progress", (event) => {
+            unlisten = await listen<SyncProgress>("sync-progress", (event) => {
                 setSyncProgress(event.payload);
             });
+            // The player flags files that fail to open, and clears them once they play
+            unlistenUnplayable = await listen<{ path: string; unplayable: boolean }>("song-unplayable-changed", (event) => {
+                const { path, unplayable } = event.payload;
+                setSongs(prev => prev.map(s => s.path === path ? { ...s, unplayable } : s));
+            });
+            // The backend watches the music directory and has already updated the cache
+            unlistenChanges = await listen<LibraryChanges>("library-changed", (event) => {
+                const { added, updated, removed } = event.payload;
+                const removedPaths = new Set(removed);
+                const updatedSongs = new Map(updated.map(s => [s.path, s]));
+                setSongs(prev => [
+                    ...prev.filter(s => !removedPaths.has(s.path)).map(s => updatedSongs.get(s.path) ?? s),
+                    ...added,
+                ]);
+                refreshCacheSize();
+            });
         };
         setup();
         refreshCacheSize();
-        return () => { if (unlisten) unlisten(); };
+        return () => {
+            if (unlisten) unlisten();
+            if (unlistenUnplayable) unlistenUnplayable();
+            if (unlistenChanges) unlistenChanges();
+        };
     }, []);
 
     async function refreshCacheSize() {
@@ -63,8 +87,9 @@ export function useLibrary(): UseLibraryReturn {
         setLoading(true);
         setSyncProgress(null);
         try {
-            const result = await invoke<Song[]>("scan_music_dir", { directory: overridePath || path });
-            setSongs(result);
+            const result = await invoke<ScanResult>("scan_music_dir", { directory: overridePath || path });
+            console.log(`Library synced: ${result.added} added, ${result.updated} updated, ${result.removed} removed`);
+            setSongs(result.songs);
         } catch (e) {
             console.error(e);
             showAlert("Error scanning: " + e, "Error");
@@ -88,8 +113,11 @@ export function useLibrary(): UseLibraryReturn {
     async function saveSettings(newPath: string, newSeekInterval: number) {
         setPath(newPath);
         setSeekInterval(newSeekInterval);
+        // Keep backend-only settings (crossfade, ...) that this form doesn't edit
+        const current = await invoke<AppSettings>("load_settings");
         await invoke("save_settings", {
             settings: {
+                ...current,
                 music_directory: newPath,
                 theme: "dark",
                 seek_interval: newSeekInterval
@@ -97,6 +125,12 @@ export function useLibrary(): UseLibraryReturn {
         });
     }
 
+    async function setWatchLibrary(enabled: boolean) {
+        setWatchLibraryState(enabled);
+        const current = await invoke<AppSettings>("load_settings");
+        await invoke("save_settings", { settings: { ...current, watch_library: enabled } });
+    }
+
     return {
         songs,
         setSongs,
@@ -111,5 +145,7 @@ export function useLibrary(): UseLibraryReturn {
         handleClearCache,
         refreshCacheSize,
         saveSettings,
+        watchLibrary,
+        setWatchLibrary,
     };
 }
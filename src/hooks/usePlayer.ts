@@ -1,7 +1,10 @@
 import { useState, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { Song, LoopMode, UsePlayerProps, UsePlayerReturn } from "../models";
+import { listen } from "@tauri-apps/api/event";
+import { Song, LoopMode, QueueState, PositionPayload, PlayerState, UsePlayerProps, UsePlayerReturn } from "../models";
 
+// The queue, shuffle, repeat and saved session live in the backend, this hook mirrors
+// them from its events
 export function usePlayer({ songs, seekInterval }: UsePlayerProps): UsePlayerReturn {
     const [queue, setQueue] = useState<Song[]>([]);
     const [currentIndex, setCurrentIndex] = useState<number>(-1);
@@ -10,42 +13,80 @@ export function usePlayer({ songs, seekInterval }: UsePlayerProps): UsePlayerRet
     const [isShuffle, setIsShuffle] = useState(false);
     const [loopMode, setLoopMode] = useState<LoopMode>("off");
     const [volume, setVolume] = useState(0.5);
-    const progressIntervalRef = useRef<number | null>(null);
-    const trackLoadedRef = useRef<boolean>(false);
+    const songsRef = useRef<Map<string, Song>>(new Map());
+    const lastPathRef = useRef<string | null>(null);
 
     const currentSong = queue[currentIndex] || null;
 
-    // Progress bar timer
     useEffect(() => {
-        if (isPlaying && currentSong) {
-            progressIntervalRef.current = window.setInterval(() => {
-                setCurrentTime(prev => {
-                    if (prev >= currentSong.duration_seconds) {
-                        nextTrack(true);
-                        return 0;
-                    }
-                    return prev + 1;
-                });
-            }, 1000);
-        } else {
-            if (progressIntervalRef.current) {
-                clearInterval(progressIntervalRef.current);
-            }
+        for (const song of songs) {
+            songsRef.current.set(song.path, song);
         }
-        return () => {
-            if (progressIntervalRef.current) {
-                clearInterval(progressIntervalRef.current);
+        // A restored queue can arrive before the library has loaded
+        setQueue(prev => prev.map(s => songsRef.current.get(s.path) ?? s));
+    }, [songs]);
+
+    useEffect(() => {
+        function toSong(path: string): Song {
+            return songsRef.current.get(path) ?? {
+                path,
+                title: path.split(/[\\/]/).pop(),
+                duration_seconds: 0,
+                file_size_bytes: 0,
+                has_album_art: false,
+            };
+        }
+
+        function applyQueue(state: QueueState) {
+            setQueue(state.tracks.map(toSong));
+            setCurrentIndex(state.current_index ?? -1);
+            setIsShuffle(state.shuffle);
+            setLoopMode(state.repeat);
+        }
+
+        // The backend restored the last session before this window loaded
+        invoke<QueueState>("get_queue").then(applyQueue).catch(e => console.error("Failed to load queue", e));
+        invoke<PlayerState>("get_player_state").then((state) => {
+            setVolume(state.volume);
+            setCurrentTime(Math.floor(state.position_ms / 1000));
+            setIsPlaying(state.state === "playing");
+            lastPathRef.current = state.path;
+        }).catch(e => console.error("Failed to load player state", e));
+
+        const unlistenQueue = listen<QueueState>("queue-changed", (event) => applyQueue(event.payload));
+        const unlistenPosition = listen<PositionPayload>("playback-position", (event) => {
+            const { path, position_ms, state } = event.payload;
+            setCurrentTime(Math.floor(position_ms / 1000));
+            setIsPlaying(state === "playing");
+            if (path && path !== lastPathRef.current && state === "playing") {
+                invoke("increment_play_count", { path }).catch(e => console.error("Failed to count play", e));
             }
+            lastPathRef.current = path;
+        });
+        return () => {
+            unlistenQueue.then((unlisten) => unlisten());
+            unlistenPosition.then((unlisten) => unlisten());
         };
-    }, [isPlaying, currentSong]);
+    }, []);
+
+    function remember(tracks: Song[]) {
+        for (const song of tracks) {
+            songsRef.current.set(song.path, song);
+        }
+    }
+
+    async function playQueue(tracks: Song[], startIndex: number | null) {
+        remember(tracks);
+        try {
+            await invoke("set_queue", { paths: tracks.map(s => s.path), startIndex });
+        } catch (e) {
+            console.error("Failed to play", e);
+        }
+    }
 
-    async function playTrackInternal(trackPath: string) {
+    async function playIndex(index: number) {
         try {
-            await invoke("play_track", { path: trackPath });
-            setCurrentTime(0);
-            setIsPlaying(true);
-            trackLoadedRef.current = true;
-            await invoke("increment_play_count", { path: trackPath });
+            await invoke("jump_to_queue_index", { index });
         } catch (e) {
             console.error("Failed to play", e);
         }
@@ -54,108 +95,79 @@ export function usePlayer({ songs, seekInterval }: UsePlayerProps): UsePlayerRet
     async function playSong(song: Song) {
         const idx = songs.findIndex(s => s.path === song.path);
         if (idx !== -1) {
-            setQueue(songs);
-            setCurrentIndex(idx);
-            await playTrackInternal(song.path);
+            await playQueue(songs, idx);
         } else {
-            setQueue([song]);
-            setCurrentIndex(0);
-            await playTrackInternal(song.path);
+            await playQueue([song], 0);
+        }
+    }
+
+    // Queued tracks are handed to the audio thread ahead of time, so they follow on gaplessly
+    async function playNext(tracks: Song[]) {
+        remember(tracks);
+        try {
+            await invoke("insert_next_tracks", { paths: tracks.map(s => s.path) });
+        } catch (e) {
+            console.error("Failed to queue", e);
+        }
+    }
+
+    async function addToQueue(tracks: Song[]) {
+        remember(tracks);
+        try {
+            await invoke("append_tracks", { paths: tracks.map(s => s.path) });
+        } catch (e) {
+            console.error("Failed to queue", e);
         }
     }
 
     async function togglePlay() {
         try {
-            if (!trackLoadedRef.current && currentSong) {
-                await invoke("play_track", { path: currentSong.path });
-                if (currentTime > 0) {
-                    await invoke("seek_track", { seconds: Math.floor(currentTime) });
-                }
-                trackLoadedRef.current = true;
-                setIsPlaying(true);
-                return;
-            }
             await invoke("toggle_playback");
-            setIsPlaying(!isPlaying);
         } catch (e) {
             console.error("Toggle failed", e);
         }
     }
 
-    function toggleShuffle() {
-        setIsShuffle(!isShuffle);
+    async function toggleShuffle() {
+        await invoke("set_shuffle", { enabled: !isShuffle });
     }
 
-    function toggleLoop() {
-        if (loopMode === "off") setLoopMode("all");
-        else if (loopMode === "all") setLoopMode("one");
-        else setLoopMode("off");
+    async function toggleLoop() {
+        const next: LoopMode = loopMode === "off" ? "all" : loopMode === "all" ? "one" : "off";
+        await invoke("set_repeat_mode", { mode: next });
     }
 
-    async function nextTrack(auto = false) {
-        if (queue.length === 0) return;
-
-        if (loopMode === "one" && auto) {
-            await seekTo(0);
-            return;
-        }
-
-        let nextIndex;
-        if (isShuffle) {
-            nextIndex = Math.floor(Math.random() * queue.length);
-            if (queue.length > 1 && nextIndex === currentIndex) {
-                nextIndex = (nextIndex + 1) % queue.length;
-            }
-        } else {
-            nextIndex = currentIndex + 1;
-        }
-
-        if (nextIndex >= queue.length) {
-            if (loopMode === "off") {
-                if (auto) {
-                    setIsPlaying(false);
-                    await invoke("stop_playback");
-                    setCurrentIndex(0);
-                    setCurrentTime(0);
-                    return;
-                }
-                nextIndex = 0;
-            } else {
-                nextIndex = 0;
-            }
-        }
-
-        setCurrentIndex(nextIndex);
-        await playTrackInternal(queue[nextIndex].path);
+    async function nextTrack() {
+        await invoke("next_track");
     }
 
     async function prevTrack() {
-        if (queue.length === 0) return;
-        let prevIndex = currentIndex - 1;
-        if (prevIndex < 0) prevIndex = queue.length - 1;
-        setCurrentIndex(prevIndex);
-        await playTrackInternal(queue[prevIndex].path);
+        await invoke("previous_track");
+    }
+
+    // Seeks answer with the position in ms they actually landed on
+    async function seek(command: string, args: Record<string, number> = {}) {
+        try {
+            const landedMs = await invoke<number>(command, args);
+            setCurrentTime(Math.floor(landedMs / 1000));
+        } catch (e) {
+            console.error("Seek failed", e);
+        }
     }
 
     async function seekTo(time: number) {
         if (!currentSong) return;
-        const newTime = Math.max(0, Math.min(time, currentSong.duration_seconds));
-        await invoke("seek_track", { seconds: Math.floor(newTime) });
-        setCurrentTime(Math.floor(newTime));
+        await seek("seek_track_ms", { positionMs: Math.round(Math.max(0, time) * 1000) });
     }
 
     async function seekForward() {
         if (!currentSong) return;
-        const newTime = Math.min(currentTime + seekInterval, currentSong.duration_seconds);
-        await invoke("seek_track", { seconds: Math.floor(newTime) });
-        setCurrentTime(Math.floor(newTime));
+        await seek("seek_relative", { deltaMs: seekInterval * 1000 });
     }
 
     async function seekBackward() {
         if (!currentSong) return;
-        const newTime = Math.max(currentTime - seekInterval, 0);
-        await invoke("seek_track", { seconds: Math.floor(newTime) });
-        setCurrentTime(Math.floor(newTime));
+        await seek("seek_relative", { deltaMs: -seekInterval * 1000 });
     }
 
     async function handleVolumeChange(newVolume: number) {
@@ -171,16 +183,9 @@ export function usePlayer({ songs, seekInterval }: UsePlayerProps): UsePlayerRet
     async function handlePlayPlaylist(playlistSongs: Song[], startIndex = 0, shuffle = false) {
         if (playlistSongs.length === 0) return;
 
-        setQueue(playlistSongs);
-        setIsShuffle(shuffle);
-
-        let idx = startIndex;
-        if (shuffle && startIndex === 0) {
-            idx = Math.floor(Math.random() * playlistSongs.length);
-        }
-
-        setCurrentIndex(idx);
-        await playTrackInternal(playlistSongs[idx].path);
+        await invoke("set_shuffle", { enabled: shuffle });
+        // Shuffling the whole playlist starts from a random track
+        await playQueue(playlistSongs, shuffle && startIndex === 0 ? null : startIndex);
     }
 
     return {
@@ -199,9 +204,10 @@ export function usePlayer({ songs, seekInterval }: UsePlayerProps): UsePlayerRet
         setIsShuffle,
         loopMode,
         setLoopMode,
-        trackLoadedRef,
-        playTrackInternal,
+        playIndex,
         playSong,
+        playNext,
+        addToQueue,
         togglePlay,
         toggleShuffle,
         toggleLoop,
@@ -1,5 +1,52 @@
+export interface ParametricBand {
+    kind: "peaking" | "low_shelf" | "high_shelf";
+    frequency: number; // Hz
+    gain_db: number;
+    q: number;
+}
+
+export interface EqualizerSettings {
+    enabled: boolean;
+    preset?: string;
+    preamp_db: number;
+    bands: number[]; // dB for 31 Hz .. 16 kHz
+    custom_bands: ParametricBand[];
+}
+
 export interface AppSettings {
     music_directory: string;
     theme: string;
     seek_interval: number;
+    crossfade_seconds: number;
+    replaygain_mode: "off" | "track" | "album";
+    replaygain_preamp_db: number;
+    equalizer: EqualizerSettings;
+    output_device: string | null;
+    playback_speed: number;
+    preserve_pitch: boolean;
+    visualizer: VisualizerSettings;
+    skip_silence: SilenceSettings;
+    stereo: StereoSettings;
+    transport_fade_ms: number;
+    skip_unplayable: boolean;
+    watch_library: boolean;
+}
+
+export interface StereoSettings {
+    balance: number;
+    mono: boolean;
+    swap_channels: boolean;
+}
+
+export interface SilenceSettings {
+    trim: boolean;
+    skip_mid_track: boolean;
+    threshold_db: number;
+    min_silence_ms: number;
+}
+
+export interface VisualizerSettings {
+    enabled: boolean;
+    bands: number;
+    fps: number;
 }
@@ -0,0 +1,8 @@
+import { Song } from "./Song";
+
+// Sent by the backend watcher when files in the music directory change
+export interface LibraryChanges {
+    added: Song[];
+    updated: Song[];
+    removed: string[];
+}
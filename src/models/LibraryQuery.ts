@@ -0,0 +1,30 @@
+import { Song } from "./Song";
+
+export type SortField =
+    | "title" | "artist" | "album" | "genre" | "year" | "track_number"
+    | "duration" | "bitrate" | "sample_rate" | "file_size" | "date_added";
+
+export interface SortKey {
+    field: SortField;
+    direction?: "asc" | "desc";
+}
+
+// Arguments of `query_library`, every filter is optional
+export interface LibraryFilter {
+    artist?: string;
+    album?: string;
+    genre?: string;
+    year_min?: number;
+    year_max?: number;
+    formats?: string[]; // file extensions like "flac"
+    bitrate_min?: number; // kbps
+    bitrate_max?: number;
+    has_lyrics?: boolean;
+    has_art?: boolean;
+}
+
+export interface LibraryPage {
+    songs: Song[];
+    total: number;
+    offset: number;
+}
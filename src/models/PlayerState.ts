@@ -0,0 +1,10 @@
+export interface PositionPayload {
+    path: string | null;
+    position_ms: number;
+    duration_ms: number | null;
+    state: "playing" | "paused" | "stopped";
+}
+
+export interface PlayerState extends PositionPayload {
+    volume: number;
+}
@@ -0,0 +1,9 @@
+import { LoopMode } from "./LoopMode";
+
+export interface QueueState {
+    tracks: string[];
+    current_index: number | null;
+    order: number[]; // play order as indices into tracks
+    shuffle: boolean;
+    repeat: LoopMode;
+}
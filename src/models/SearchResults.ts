@@ -0,0 +1,20 @@
+import { Song } from "./Song";
+
+export interface AlbumResult {
+    title: string;
+    artist?: string;
+    song_count: number;
+    cover_handle?: string;
+}
+
+export interface ArtistResult {
+    name: string;
+    song_count: number;
+}
+
+// Returned by `search_library`, best matches first within each group
+export interface SearchResults {
+    songs: Song[];
+    albums: AlbumResult[];
+    artists: ArtistResult[];
+}
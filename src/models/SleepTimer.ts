@@ -0,0 +1,14 @@
+// Returned by `get_sleep_timer` while a timer is set
+export interface SleepTimerStatus {
+    action: "stop" | "quit";
+    remaining_ms: number | null; // unknown while more than one track is left
+    tracks_left: number | null; // set when counting tracks rather than time
+    fade_seconds: number;
+}
+
+// The active timer as the player controls show it
+export interface SleepTimerState {
+    action: "stop" | "quit";
+    endTime: number | null;
+    tracksLeft: number | null;
+}
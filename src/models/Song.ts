@@ -12,7 +12,13 @@ export interface Song {
     bits_per_sample?: number;
     channels?: number;
     file_size_bytes: number;
+    modified_secs?: number;
     has_album_art: boolean;
     cover_handle?: string;
     lyrics?: string;
+    replaygain_track_gain?: number; // dB
+    replaygain_track_peak?: number;
+    replaygain_album_gain?: number; // dB
+    replaygain_album_peak?: number;
+    unplayable?: boolean; // failed to open or decode last time it was played
 }
@@ -0,0 +1,17 @@
+import { Song } from "./Song";
+
+// Rescans only read new and changed files, `total` counts those
+export interface SyncProgress {
+    current: number;
+    total: number;
+    added: number;
+    updated: number;
+    removed: number;
+}
+
+export interface ScanResult {
+    songs: Song[];
+    added: number;
+    updated: number;
+    removed: number;
+}
@@ -1,4 +1,5 @@
 import { Song } from "./Song";
+import { SyncProgress } from "./SyncProgress";
 
 export interface UseLibraryReturn {
     songs: Song[];
@@ -8,10 +9,12 @@ export interface UseLibraryReturn {
     setPath: React.Dispatch<React.SetStateAction<string>>;
     seekInterval: number;
     setSeekInterval: React.Dispatch<React.SetStateAction<number>>;
-    syncProgress: { current: number; total: number } | null;
+    syncProgress: SyncProgress | null;
     cacheSize: number;
     scanMusic: (overridePath?: string) => Promise<void>;
     handleClearCache: () => Promise<void>;
     refreshCacheSize: () => Promise<void>;
     saveSettings: (newPath: string, newSeekInterval: number) => Promise<void>;
+    watchLibrary: boolean;
+    setWatchLibrary: (enabled: boolean) => Promise<void>;
 }
@@ -22,13 +22,14 @@ export interface UsePlayerReturn {
     setIsShuffle: React.Dispatch<React.SetStateAction<boolean>>;
     loopMode: LoopMode;
     setLoopMode: React.Dispatch<React.SetStateAction<LoopMode>>;
-    trackLoadedRef: React.MutableRefObject<boolean>;
-    playTrackInternal: (trackPath: string) => Promise<void>;
+    playIndex: (index: number) => Promise<void>;
     playSong: (song: Song) => Promise<void>;
+    playNext: (tracks: Song[]) => Promise<void>;
+    addToQueue: (tracks: Song[]) => Promise<void>;
     togglePlay: () => Promise<void>;
-    toggleShuffle: () => void;
-    toggleLoop: () => void;
-    nextTrack: (auto?: boolean) => Promise<void>;
+    toggleShuffle: () => Promise<void>;
+    toggleLoop: () => Promise<void>;
+    nextTrack: () => Promise<void>;
     prevTrack: () => Promise<void>;
     seekTo: (time: number) => Promise<void>;
     seekForward: () => Promise<void>;
@@ -1,9 +1,14 @@
 export type { Song } from "./Song";
 export type { Playlist } from "./Playlist";
+export type { SyncProgress, ScanResult } from "./SyncProgress";
+export type { LibraryChanges } from "./LibraryChanges";
+export type { SortField, SortKey, LibraryFilter, LibraryPage } from "./LibraryQuery";
+export type { AlbumResult, ArtistResult, SearchResults } from "./SearchResults";
 export type { AppSettings } from "./AppSettings";
 export type { LoopMode } from "./LoopMode";
-export type { PlaybackState } from "./PlaybackState";
+export type { QueueState } from "./QueueState";
+export type { SleepTimerStatus, SleepTimerState } from "./SleepTimer";
+export type { PositionPayload, PlayerState } from "./PlayerState";
 export type { UsePlayerProps, UsePlayerReturn } from "./UsePlayerTypes";
 export type { UsePlaylistsProps, UsePlaylistsReturn } from "./UsePlaylistsTypes";
 export type { UseLibraryReturn } from "./UseLibraryTypes";
-export type { UsePlaybackPersistenceProps } from "./UsePlaybackPersistenceTypes";